@@ -9,7 +9,6 @@ cargo build
 
 popd
 
-../../target/debug/pcc "$(cat ./main.c)" > tmp.s
-cc -no-pie -o tmp tmp.s
+../../target/debug/pcc -o tmp ./main.c
 ./tmp
 echo
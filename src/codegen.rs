@@ -1,44 +1,126 @@
-use crate::parser::{Node, NodeKind, Program, Type};
+use crate::diagnostic::Diagnostic;
+use crate::parser::{BinaryOp, Node, NodeKind, Program, Type};
+use std::convert::TryFrom;
+use std::io::Write;
 
 macro_rules! p {
-  ($($arg:tt)*) => ({println!($($arg)*);})
+  ($ctx:expr, $($arg:tt)*) => ({writeln!($ctx.out, $($arg)*)?;})
 }
 
 macro_rules! emit {
-  ($($arg:tt)*) => ({print!("\t");p!($($arg)*);})
+  ($ctx:expr, $($arg:tt)*) => ({write!($ctx.out, "\t")?;p!($ctx, $($arg)*);})
 }
 
-const ARGREG1: &'static [&str] = &["dil", "sil", "dl", "cl", "r8b", "r9b"];
-const ARGREG8: &'static [&str] = &["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
+type CodegenResult = Result<(), Diagnostic>;
 
-fn load(ty: &Type) {
-    emit!("pop rax");
-    if ty.size() == 1 {
-        emit!("movsx rax, byte ptr [rax]");
-    } else {
-        emit!("mov rax, [rax]");
-    }
-    emit!("push rax");
-}
+const ARGREG1: &[&str] = &["dil", "sil", "dl", "cl", "r8b", "r9b"];
+const ARGREG2: &[&str] = &["di", "si", "dx", "cx", "r8w", "r9w"];
+const ARGREG4: &[&str] = &["edi", "esi", "edx", "ecx", "r8d", "r9d"];
+const ARGREG8: &[&str] = &["rdi", "rsi", "rdx", "rcx", "r8", "r9"];
 
-fn store(ty: &Type) {
-    emit!("pop rdi");
-    emit!("pop rax");
-    if ty.size() == 1 {
-        emit!("mov [rax], dil");
-    } else {
-        emit!("mov [rax], rdi");
+/// case の値が密集していてジャンプテーブルを使う方が良いかどうか
+fn is_dense(cases: &[i64]) -> bool {
+    if cases.len() < 4 {
+        return false;
     }
-    emit!("push rdi");
+    let min = i128::from(*cases.iter().min().unwrap());
+    let max = i128::from(*cases.iter().max().unwrap());
+    let range = max - min + 1;
+    return range <= 1024 && range <= cases.len() as i128 * 3;
 }
 
-struct CodegenContext {
+struct CodegenContext<'w> {
+    out: &'w mut dyn Write,
     label_id: u32,
+    // 生成中の関数名 (goto のラベル名に使う)
+    function_name: String,
+    // break と continue の飛び先のラベルで、最も内側のものが末尾になる
+    break_labels: Vec<String>,
+    continue_labels: Vec<String>,
+    // 生成中の switch 文のラベル番号で、最も内側のものが末尾になる
+    switch_ids: Vec<u32>,
 }
 
-impl CodegenContext {
-    fn new() -> CodegenContext {
-        return CodegenContext { label_id: 0 };
+impl<'w> CodegenContext<'w> {
+    fn new(out: &'w mut dyn Write) -> CodegenContext<'w> {
+        return CodegenContext {
+            out,
+            label_id: 0,
+            function_name: String::new(),
+            break_labels: Vec::new(),
+            continue_labels: Vec::new(),
+            switch_ids: Vec::new(),
+        };
+    }
+
+    fn load(&mut self, ty: &Type) -> CodegenResult {
+        emit!(self, "pop rax");
+        // NOTE: 8 バイトに満たない整数は型の符号に合わせて拡張する
+        match (ty.size(), ty.is_unsigned()) {
+            (1, false) => emit!(self, "movsx rax, byte ptr [rax]"),
+            (1, true) => emit!(self, "movzx rax, byte ptr [rax]"),
+            (2, false) => emit!(self, "movsx rax, word ptr [rax]"),
+            (2, true) => emit!(self, "movzx rax, word ptr [rax]"),
+            (4, false) => emit!(self, "movsxd rax, dword ptr [rax]"),
+            // NOTE: 32 ビットレジスタへの mov は上位 32 ビットを 0 にする
+            (4, true) => emit!(self, "mov eax, [rax]"),
+            _ => emit!(self, "mov rax, [rax]"),
+        }
+        emit!(self, "push rax");
+        return Ok(());
+    }
+
+    fn store(&mut self, ty: &Type) -> CodegenResult {
+        emit!(self, "pop rdi");
+        emit!(self, "pop rax");
+        if let Type::Struct(_) = ty {
+            // NOTE: 構造体は RDI が指す先から 1 バイトずつコピーする
+            for i in 0..ty.size() {
+                emit!(self, "mov r8b, [rdi+{}]", i);
+                emit!(self, "mov [rax+{}], r8b", i);
+            }
+            emit!(self, "push rax");
+            return Ok(());
+        }
+        if let Type::Bool = ty {
+            // NOTE: _Bool には 0 か 1 だけを格納する
+            emit!(self, "cmp rdi, 0");
+            emit!(self, "setne dil");
+            emit!(self, "movzx rdi, dil");
+        }
+        match ty.size() {
+            1 => emit!(self, "mov [rax], dil"),
+            2 => emit!(self, "mov [rax], di"),
+            4 => emit!(self, "mov [rax], edi"),
+            _ => emit!(self, "mov [rax], rdi"),
+        }
+        emit!(self, "push rdi");
+        return Ok(());
+    }
+
+    /// RAX の値を ty の型に変換する
+    ///
+    /// NOTE: 整数は常に 64 ビットに符号拡張またはゼロ拡張した値で扱う
+    fn cast(&mut self, ty: &Type) -> CodegenResult {
+        if let Type::Bool = ty {
+            emit!(self, "cmp rax, 0");
+            emit!(self, "setne al");
+            emit!(self, "movzx eax, al");
+            return Ok(());
+        }
+        if !ty.is_integer() {
+            return Ok(());
+        }
+        match (ty.size(), ty.is_unsigned()) {
+            (1, false) => emit!(self, "movsx rax, al"),
+            (1, true) => emit!(self, "movzx eax, al"),
+            (2, false) => emit!(self, "movsx rax, ax"),
+            (2, true) => emit!(self, "movzx eax, ax"),
+            (4, false) => emit!(self, "movsxd rax, eax"),
+            (4, true) => emit!(self, "mov eax, eax"),
+            _ => {}
+        }
+        return Ok(());
     }
 
     fn generate_label_id(&mut self) -> u32 {
@@ -47,147 +129,385 @@ impl CodegenContext {
         return id;
     }
 
-    fn gen_addr(&mut self, node: &Node) {
+    fn gen_addr(&mut self, node: &Node) -> CodegenResult {
         match &node.kind {
             NodeKind::Variable(var) => {
                 if var.is_local {
-                    emit!("mov rax, rbp");
-                    emit!("sub rax, {}", var.offset);
-                    emit!("push rax");
+                    emit!(self, "mov rax, rbp");
+                    emit!(self, "sub rax, {}", var.offset);
+                    emit!(self, "push rax");
                 } else {
-                    emit!("push offset {}\n", var.name);
+                    emit!(self, "push offset {}", var.name);
                 }
             }
             NodeKind::Deref(node) => {
-                self.gen(&node);
+                self.gen(node)?;
+            }
+            NodeKind::Member { target, member } => {
+                self.gen_addr(target)?;
+                emit!(self, "pop rax");
+                emit!(self, "add rax, {}", member.offset);
+                emit!(self, "push rax");
             }
-            _ => panic!("変数ではありません"),
+            _ => return Err(Diagnostic::error("変数ではありません").with_span(node.span)),
         }
+        return Ok(());
     }
 
-    fn gen_lvar(&mut self, node: &Node) {
+    fn gen_lvar(&mut self, node: &Node) -> CodegenResult {
         if let Some(Type::Array(..)) = node.ty {
-            panic!("左辺値ではありません");
+            return Err(Diagnostic::error("左辺値ではありません").with_span(node.span));
         }
-        self.gen_addr(node);
+        return self.gen_addr(node);
     }
 
-    pub fn gen_binary_ops(&mut self, lhs: &Box<Node>, rhs: &Box<Node>, f: impl Fn()) {
-        self.gen(lhs);
-        self.gen(rhs);
-        emit!("pop rdi");
-        emit!("pop rax");
-        f();
-        emit!("push rax");
+    /// 両辺を評価して左辺を RAX に、右辺を RDI に入れる
+    fn gen_binary_operands(&mut self, lhs: &Node, rhs: &Node) -> CodegenResult {
+        self.gen(lhs)?;
+        self.gen(rhs)?;
+        emit!(self, "pop rdi");
+        emit!(self, "pop rax");
+        return Ok(());
     }
 
-    pub fn gen(&mut self, node: &Node) {
+    /// RAX と RDI に対して二項演算を行い、結果を RAX に入れる
+    ///
+    /// ty は演算を行う型で、ポインタの加減算のスケールや符号の有無の判定に使う
+    fn gen_binary_op(&mut self, op: BinaryOp, ty: Option<&Type>) -> CodegenResult {
+        let unsigned = matches!(ty, Some(ty) if ty.is_unsigned());
+        match op {
+            BinaryOp::Add | BinaryOp::Sub => {
+                if let Some(Type::Pointer(base)) | Some(Type::Array(base, _)) = ty {
+                    emit!(self, "imul rdi, {}", base.size());
+                }
+                if op == BinaryOp::Add {
+                    emit!(self, "add rax, rdi");
+                } else {
+                    emit!(self, "sub rax, rdi");
+                }
+            }
+            BinaryOp::Mul => emit!(self, "imul rax, rdi"),
+            BinaryOp::Div | BinaryOp::Mod => {
+                if unsigned {
+                    emit!(self, "mov rdx, 0");
+                    emit!(self, "div rdi");
+                } else {
+                    emit!(self, "cqo");
+                    emit!(self, "idiv rdi");
+                }
+                if op == BinaryOp::Mod {
+                    emit!(self, "mov rax, rdx");
+                }
+            }
+            BinaryOp::Shl => {
+                emit!(self, "mov rcx, rdi");
+                emit!(self, "shl rax, cl");
+            }
+            BinaryOp::Shr => {
+                emit!(self, "mov rcx, rdi");
+                if unsigned {
+                    emit!(self, "shr rax, cl");
+                } else {
+                    emit!(self, "sar rax, cl");
+                }
+            }
+            BinaryOp::BitAnd => emit!(self, "and rax, rdi"),
+            BinaryOp::BitOr => emit!(self, "or rax, rdi"),
+            BinaryOp::BitXor => emit!(self, "xor rax, rdi"),
+        }
+        return Ok(());
+    }
+
+    fn gen_binary(&mut self, op: BinaryOp, lhs: &Node, rhs: &Node, node: &Node) -> CodegenResult {
+        self.gen_binary_operands(lhs, rhs)?;
+        self.gen_binary_op(op, node.ty.as_ref())?;
+        if let Some(ty) = &node.ty {
+            self.cast(ty)?;
+        }
+        emit!(self, "push rax");
+        return Ok(());
+    }
+
+    /// RAX の値で case に分岐するジャンプテーブルを生成する
+    ///
+    /// テーブルは `.rodata` に置き、case のない値は default_label に飛ぶ
+    fn gen_jump_table(
+        &mut self,
+        cases: &[i64],
+        label_id: u32,
+        default_label: &str,
+    ) -> CodegenResult {
+        let min = *cases.iter().min().unwrap();
+        let max = *cases.iter().max().unwrap();
+        // NOTE: 最小値を引いてから符号なしで比較すれば、範囲外の値を一度に弾ける
+        emit!(self, "mov rdi, {}", min);
+        emit!(self, "sub rax, rdi");
+        emit!(self, "mov rdi, {}", max.wrapping_sub(min));
+        emit!(self, "cmp rax, rdi");
+        emit!(self, "ja  {}", default_label);
+        emit!(self, "lea rdi, .L.jtable.{}[rip]", label_id);
+        emit!(self, "jmp [rdi+rax*8]");
+
+        p!(self, ".section .rodata");
+        emit!(self, ".align 8");
+        p!(self, ".L.jtable.{}:", label_id);
+        for value in min..=max {
+            match cases.iter().position(|v| *v == value) {
+                Some(i) => emit!(self, ".quad .L.case.{}.{}", label_id, i),
+                None => emit!(self, ".quad {}", default_label),
+            }
+        }
+        p!(self, ".text");
+        return Ok(());
+    }
+
+    /// ループの本体を生成する
+    ///
+    /// break は `.L.end.<id>` に、continue は本体の直後の `.L.continue.<id>` に飛ぶ
+    fn gen_loop_body(&mut self, then: &Node, label_id: u32) -> CodegenResult {
+        self.break_labels.push(format!(".L.end.{}", label_id));
+        self.continue_labels
+            .push(format!(".L.continue.{}", label_id));
+        self.gen(then)?;
+        self.break_labels.pop();
+        self.continue_labels.pop();
+        p!(self, ".L.continue.{}:", label_id);
+        return Ok(());
+    }
+
+    pub fn gen(&mut self, node: &Node) -> CodegenResult {
         match &node.kind {
             NodeKind::Null => {}
             NodeKind::Number(n) => {
-                emit!("push {}", n);
-            }
-            NodeKind::Add { lhs, rhs } => {
-                self.gen_binary_ops(lhs, rhs, || {
-                    match &node.ty {
-                        Some(Type::Pointer(base)) | Some(Type::Array(base, _)) => {
-                            emit!("imul rdi, {}", base.size());
-                        }
-                        _ => {}
-                    }
-                    emit!("add rax, rdi");
-                });
-            }
-            NodeKind::Sub { lhs, rhs } => {
-                self.gen_binary_ops(lhs, rhs, || {
-                    match &node.ty {
-                        Some(Type::Pointer(base)) | Some(Type::Array(base, _)) => {
-                            emit!("imul rdi, {}", base.size());
-                        }
-                        _ => {}
-                    }
-                    emit!("sub rax, rdi");
-                });
-            }
-            NodeKind::Mul { lhs, rhs } => {
-                self.gen_binary_ops(lhs, rhs, || emit!("imul rax, rdi"));
-            }
-            NodeKind::Div { lhs, rhs } => self.gen_binary_ops(lhs, rhs, || {
-                emit!("cqo");
-                emit!("idiv rdi");
-            }),
-            NodeKind::Equal { lhs, rhs } => self.gen_binary_ops(lhs, rhs, || {
-                emit!("cmp rax, rdi");
-                emit!("sete al");
-                emit!("movzb rax, al");
-            }),
-            NodeKind::NotEqual { lhs, rhs } => self.gen_binary_ops(lhs, rhs, || {
-                emit!("cmp rax, rdi");
-                emit!("setne al");
-                emit!("movzb rax, al");
-            }),
-            NodeKind::LessThan { lhs, rhs } => self.gen_binary_ops(lhs, rhs, || {
-                emit!("cmp rax, rdi");
-                emit!("setl al");
-                emit!("movzb rax, al");
-            }),
-            NodeKind::LessThanEqual { lhs, rhs } => self.gen_binary_ops(lhs, rhs, || {
-                emit!("cmp rax, rdi");
-                emit!("setle al");
-                emit!("movzb rax, al");
-            }),
+                // NOTE: push の即値は 32 ビットまでなので、それより大きい値は RAX を経由する
+                if i32::try_from(*n).is_ok() {
+                    emit!(self, "push {}", n);
+                } else {
+                    emit!(self, "mov rax, {}", n);
+                    emit!(self, "push rax");
+                }
+            }
+            NodeKind::Add { lhs, rhs } => self.gen_binary(BinaryOp::Add, lhs, rhs, node)?,
+            NodeKind::Sub { lhs, rhs } => self.gen_binary(BinaryOp::Sub, lhs, rhs, node)?,
+            NodeKind::Mul { lhs, rhs } => self.gen_binary(BinaryOp::Mul, lhs, rhs, node)?,
+            NodeKind::Div { lhs, rhs } => self.gen_binary(BinaryOp::Div, lhs, rhs, node)?,
+            NodeKind::Mod { lhs, rhs } => self.gen_binary(BinaryOp::Mod, lhs, rhs, node)?,
+            NodeKind::Shl { lhs, rhs } => self.gen_binary(BinaryOp::Shl, lhs, rhs, node)?,
+            NodeKind::Shr { lhs, rhs } => self.gen_binary(BinaryOp::Shr, lhs, rhs, node)?,
+            NodeKind::BitAnd { lhs, rhs } => self.gen_binary(BinaryOp::BitAnd, lhs, rhs, node)?,
+            NodeKind::BitOr { lhs, rhs } => self.gen_binary(BinaryOp::BitOr, lhs, rhs, node)?,
+            NodeKind::BitXor { lhs, rhs } => self.gen_binary(BinaryOp::BitXor, lhs, rhs, node)?,
+            NodeKind::BitNot(target) => {
+                self.gen(target)?;
+                emit!(self, "pop rax");
+                emit!(self, "not rax");
+                if let Some(ty) = &node.ty {
+                    self.cast(ty)?;
+                }
+                emit!(self, "push rax");
+            }
+            NodeKind::Cast(target) => {
+                self.gen(target)?;
+                emit!(self, "pop rax");
+                self.cast(node.ty.as_ref().unwrap())?;
+                emit!(self, "push rax");
+            }
+            NodeKind::Equal { lhs, rhs } => {
+                self.gen_binary_operands(lhs, rhs)?;
+                emit!(self, "cmp rax, rdi");
+                emit!(self, "sete al");
+                emit!(self, "movzb rax, al");
+                emit!(self, "push rax");
+            }
+            NodeKind::NotEqual { lhs, rhs } => {
+                self.gen_binary_operands(lhs, rhs)?;
+                emit!(self, "cmp rax, rdi");
+                emit!(self, "setne al");
+                emit!(self, "movzb rax, al");
+                emit!(self, "push rax");
+            }
+            NodeKind::LessThan { lhs, rhs } => {
+                self.gen_binary_operands(lhs, rhs)?;
+                emit!(self, "cmp rax, rdi");
+                if matches!(&lhs.ty, Some(ty) if ty.is_unsigned()) {
+                    emit!(self, "setb al");
+                } else {
+                    emit!(self, "setl al");
+                }
+                emit!(self, "movzb rax, al");
+                emit!(self, "push rax");
+            }
+            NodeKind::LessThanEqual { lhs, rhs } => {
+                self.gen_binary_operands(lhs, rhs)?;
+                emit!(self, "cmp rax, rdi");
+                if matches!(&lhs.ty, Some(ty) if ty.is_unsigned()) {
+                    emit!(self, "setbe al");
+                } else {
+                    emit!(self, "setle al");
+                }
+                emit!(self, "movzb rax, al");
+                emit!(self, "push rax");
+            }
+            NodeKind::LogAnd { lhs, rhs } => {
+                let label_id = self.generate_label_id();
+                self.gen(lhs)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
+                emit!(self, "je  .L.false.{}", label_id);
+                self.gen(rhs)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
+                emit!(self, "je  .L.false.{}", label_id);
+                emit!(self, "push 1");
+                emit!(self, "jmp .L.end.{}", label_id);
+                p!(self, ".L.false.{}:", label_id);
+                emit!(self, "push 0");
+                p!(self, ".L.end.{}:", label_id);
+            }
+            NodeKind::LogOr { lhs, rhs } => {
+                let label_id = self.generate_label_id();
+                self.gen(lhs)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
+                emit!(self, "jne .L.true.{}", label_id);
+                self.gen(rhs)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
+                emit!(self, "jne .L.true.{}", label_id);
+                emit!(self, "push 0");
+                emit!(self, "jmp .L.end.{}", label_id);
+                p!(self, ".L.true.{}:", label_id);
+                emit!(self, "push 1");
+                p!(self, ".L.end.{}:", label_id);
+            }
+            NodeKind::Not(target) => {
+                self.gen(target)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
+                emit!(self, "sete al");
+                emit!(self, "movzb rax, al");
+                emit!(self, "push rax");
+            }
+            NodeKind::Cond { cond, then, els } => {
+                let label_id = self.generate_label_id();
+                self.gen(cond)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
+                emit!(self, "je  .L.else.{}", label_id);
+                self.gen(then)?;
+                emit!(self, "jmp .L.end.{}", label_id);
+                p!(self, ".L.else.{}:", label_id);
+                self.gen(els)?;
+                p!(self, ".L.end.{}:", label_id);
+            }
+            NodeKind::Comma { lhs, rhs } => {
+                self.gen(lhs)?;
+                // NOTE: 左辺の値は捨てる
+                emit!(self, "add rsp, 8");
+                self.gen(rhs)?;
+            }
             NodeKind::Assign { lhs, rhs } => {
-                self.gen_lvar(lhs);
-                self.gen(rhs);
-                store(&node.ty.as_ref().unwrap());
-                return;
+                self.gen_lvar(lhs)?;
+                self.gen(rhs)?;
+                self.store(node.ty.as_ref().unwrap())?;
+            }
+            NodeKind::AssignOp {
+                op,
+                lhs,
+                rhs,
+                op_ty,
+            } => {
+                let ty = node.ty.as_ref().unwrap();
+                // NOTE: 左辺値のアドレスは一度だけ評価し、スタック上で複製して使う
+                self.gen_lvar(lhs)?;
+                emit!(self, "push [rsp]");
+                self.load(ty)?;
+                self.gen(rhs)?;
+                emit!(self, "pop rdi");
+                emit!(self, "pop rax");
+                // NOTE: 左辺の値を演算を行う型に変換し、結果を左辺の型に戻す
+                if let Some(op_ty) = op_ty {
+                    self.cast(op_ty)?;
+                    self.gen_binary_op(*op, Some(op_ty))?;
+                    self.cast(op_ty)?;
+                } else {
+                    self.gen_binary_op(*op, None)?;
+                }
+                self.cast(ty)?;
+                emit!(self, "push rax");
+                self.store(ty)?;
+            }
+            NodeKind::PostInc(target) | NodeKind::PostDec(target) => {
+                let ty = node.ty.as_ref().unwrap();
+                let step = match ty {
+                    Type::Pointer(base) => base.size(),
+                    _ => 1,
+                };
+                self.gen_lvar(target)?;
+                emit!(self, "push [rsp]");
+                self.load(ty)?;
+                // NOTE: 更新前の値を式の値として残し、その上で更新後の値を格納する
+                emit!(self, "pop rdi");
+                emit!(self, "pop rax");
+                emit!(self, "push rdi");
+                emit!(self, "push rax");
+                if let NodeKind::PostInc(_) = node.kind {
+                    emit!(self, "add rdi, {}", step);
+                } else {
+                    emit!(self, "sub rdi, {}", step);
+                }
+                emit!(self, "push rdi");
+                self.store(ty)?;
+                emit!(self, "pop rdi");
             }
             NodeKind::Variable(_) => {
-                self.gen_addr(node);
+                self.gen_addr(node)?;
                 match &node.ty {
-                    Some(Type::Array(..)) => {}
+                    Some(Type::Array(..)) | Some(Type::Struct(_)) => {}
                     _ => {
-                        load(&node.ty.as_ref().unwrap());
+                        self.load(node.ty.as_ref().unwrap())?;
                     }
                 }
             }
             NodeKind::Return(target) => {
-                self.gen(&target);
-                emit!("pop rax");
-                emit!("mov rsp, rbp");
-                emit!("pop rbp");
-                emit!("ret");
+                if let Some(target) = target {
+                    self.gen(target)?;
+                    emit!(self, "pop rax");
+                }
+                emit!(self, "mov rsp, rbp");
+                emit!(self, "pop rbp");
+                emit!(self, "ret");
             }
             NodeKind::If { cond, then, els } => {
                 let label_id = self.generate_label_id();
-                self.gen(&cond);
-                emit!("pop rax");
-                emit!("cmp rax, 0");
+                self.gen(cond)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
                 match els {
                     Some(els) => {
-                        emit!("je  .L.else.{}", label_id);
-                        self.gen(&then);
-                        emit!("jmp .L.end.{}", label_id);
-                        p!(".L.else.{}:", label_id);
-                        self.gen(&els);
+                        emit!(self, "je  .L.else.{}", label_id);
+                        self.gen(then)?;
+                        emit!(self, "jmp .L.end.{}", label_id);
+                        p!(self, ".L.else.{}:", label_id);
+                        self.gen(els)?;
                     }
                     None => {
-                        emit!("je  .L.end.{}", label_id);
-                        self.gen(&then);
+                        emit!(self, "je  .L.end.{}", label_id);
+                        self.gen(then)?;
                     }
                 }
-                p!(".L.end.{}:", label_id);
+                p!(self, ".L.end.{}:", label_id);
             }
             NodeKind::While { cond, then } => {
                 let label_id = self.generate_label_id();
-                p!(".L.begin.{}:", label_id);
-                self.gen(&cond);
-                emit!("pop rax");
-                emit!("cmp rax, 0");
-                emit!("je  .L.end.{}", label_id);
-                self.gen(&then);
-                emit!("jmp .L.begin.{}", label_id);
-                p!(".L.end.{}:", label_id);
+                p!(self, ".L.begin.{}:", label_id);
+                self.gen(cond)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
+                emit!(self, "je  .L.end.{}", label_id);
+                self.gen_loop_body(then, label_id)?;
+                emit!(self, "jmp .L.begin.{}", label_id);
+                p!(self, ".L.end.{}:", label_id);
             }
             NodeKind::For {
                 init,
@@ -197,123 +517,271 @@ impl CodegenContext {
             } => {
                 let label_id = self.generate_label_id();
                 if let Some(init) = init {
-                    self.gen(&init);
+                    self.gen(init)?;
                 }
-                p!(".L.begin.{}:", label_id);
+                p!(self, ".L.begin.{}:", label_id);
                 if let Some(cond) = cond {
-                    self.gen(&cond);
-                    emit!("pop rax");
-                    emit!("cmp rax, 0");
-                    emit!("je  .L.end.{}", label_id);
+                    self.gen(cond)?;
+                    emit!(self, "pop rax");
+                    emit!(self, "cmp rax, 0");
+                    emit!(self, "je  .L.end.{}", label_id);
                 }
-                self.gen(&then);
+                self.gen_loop_body(then, label_id)?;
                 if let Some(inc) = inc {
-                    self.gen(&inc);
+                    self.gen(inc)?;
                 }
-                emit!("jmp .L.begin.{}", label_id);
-                p!(".L.end.{}:", label_id);
+                emit!(self, "jmp .L.begin.{}", label_id);
+                p!(self, ".L.end.{}:", label_id);
+            }
+            NodeKind::DoWhile { then, cond } => {
+                let label_id = self.generate_label_id();
+                p!(self, ".L.begin.{}:", label_id);
+                self.gen_loop_body(then, label_id)?;
+                self.gen(cond)?;
+                emit!(self, "pop rax");
+                emit!(self, "cmp rax, 0");
+                emit!(self, "jne .L.begin.{}", label_id);
+                p!(self, ".L.end.{}:", label_id);
+            }
+            NodeKind::Switch {
+                cond,
+                then,
+                cases,
+                has_default,
+            } => {
+                let label_id = self.generate_label_id();
+                let default_label = if *has_default {
+                    format!(".L.default.{}", label_id)
+                } else {
+                    format!(".L.end.{}", label_id)
+                };
+                self.gen(cond)?;
+                emit!(self, "pop rax");
+                if is_dense(cases) {
+                    self.gen_jump_table(cases, label_id, &default_label)?;
+                } else {
+                    for (i, value) in cases.iter().enumerate() {
+                        emit!(self, "mov rdi, {}", value);
+                        emit!(self, "cmp rax, rdi");
+                        emit!(self, "je  .L.case.{}.{}", label_id, i);
+                    }
+                    emit!(self, "jmp {}", default_label);
+                }
+
+                self.break_labels.push(format!(".L.end.{}", label_id));
+                self.switch_ids.push(label_id);
+                self.gen(then)?;
+                self.switch_ids.pop();
+                self.break_labels.pop();
+                p!(self, ".L.end.{}:", label_id);
+            }
+            NodeKind::Case { index, stmt } => {
+                p!(
+                    self,
+                    ".L.case.{}.{}:",
+                    self.switch_ids.last().unwrap(),
+                    index
+                );
+                self.gen(stmt)?;
+            }
+            NodeKind::Default(stmt) => {
+                p!(self, ".L.default.{}:", self.switch_ids.last().unwrap());
+                self.gen(stmt)?;
+            }
+            NodeKind::Break => {
+                let label = self.break_labels.last().unwrap().clone();
+                emit!(self, "jmp {}", label);
+            }
+            NodeKind::Continue => {
+                let label = self.continue_labels.last().unwrap().clone();
+                emit!(self, "jmp {}", label);
+            }
+            NodeKind::Goto(name) => {
+                emit!(self, "jmp .L.label.{}.{}", self.function_name, name);
+            }
+            NodeKind::Label { name, stmt } => {
+                p!(self, ".L.label.{}.{}:", self.function_name, name);
+                self.gen(stmt)?;
             }
             NodeKind::Block(nodes) => {
                 for node in nodes {
-                    self.gen(&node);
+                    self.gen(node)?;
                 }
             }
             NodeKind::FunCall { name, args } => {
                 for arg in args {
-                    self.gen(&arg);
+                    self.gen(arg)?;
                 }
                 for i in (0..args.len()).rev() {
-                    emit!("pop {}", ARGREG8[i]);
+                    emit!(self, "pop {}", ARGREG8[i]);
                 }
 
                 // NOTE: 関数呼び出しをする前にRSPが 16 の倍数でなければならないため
                 let label_id = self.generate_label_id();
-                emit!("mov rax, rsp");
-                emit!("and rax, 15");
-                emit!("jnz .L.call.{}", label_id);
-                emit!("mov rax, 0");
-                emit!("call {}", name);
-                emit!("jmp .L.end.{}", label_id);
-                p!(".L.call.{}:", label_id);
-                emit!("sub rsp, 8");
-                emit!("mov rax, 0");
+                emit!(self, "mov rax, rsp");
+                emit!(self, "and rax, 15");
+                emit!(self, "jnz .L.call.{}", label_id);
+                emit!(self, "mov rax, 0");
+                emit!(self, "call {}", name);
+                emit!(self, "jmp .L.end.{}", label_id);
+                p!(self, ".L.call.{}:", label_id);
+                emit!(self, "sub rsp, 8");
+                emit!(self, "mov rax, 0");
                 // NOTE: 浮動小数点がまだないため関数呼出前に AL に 0 をセットする
-                emit!("mov al, 0");
-                emit!("call {}", name);
-                emit!("add rsp, 8");
-                p!(".L.end.{}:", label_id);
-                emit!("push rax");
+                emit!(self, "mov al, 0");
+                emit!(self, "call {}", name);
+                emit!(self, "add rsp, 8");
+                p!(self, ".L.end.{}:", label_id);
+                // NOTE: 8 バイトに満たない戻り値は上位ビットが不定なので、戻り値の型に合わせて拡張する
+                self.cast(node.ty.as_ref().unwrap())?;
+                emit!(self, "push rax");
             }
             NodeKind::Addr(target) => {
-                self.gen_addr(&target);
+                self.gen_addr(target)?;
             }
             NodeKind::Deref(target) => {
-                self.gen(&target);
+                self.gen(target)?;
                 match &node.ty {
-                    Some(Type::Array(..)) => {}
+                    Some(Type::Array(..)) | Some(Type::Struct(_)) => {}
                     _ => {
-                        load(&node.ty.as_ref().unwrap());
+                        self.load(node.ty.as_ref().unwrap())?;
+                    }
+                }
+            }
+            NodeKind::Member { .. } => {
+                self.gen_addr(node)?;
+                match &node.ty {
+                    Some(Type::Array(..)) | Some(Type::Struct(_)) => {}
+                    _ => {
+                        self.load(node.ty.as_ref().unwrap())?;
                     }
                 }
             }
         }
+        return Ok(());
     }
 }
 
-pub fn codegen(program: &Program) {
-    let mut ctx = CodegenContext::new();
-
-    p!(".intel_syntax noprefix");
+fn gen_program(ctx: &mut CodegenContext, program: &Program) -> CodegenResult {
+    p!(ctx, ".intel_syntax noprefix");
 
-    p!(".data");
+    p!(ctx, ".data");
     for global in &program.globals {
-        p!("{}:", global.name);
-        if let Some(s) = global.content {
-            for c in s.chars() {
-                emit!(".byte {}", c as i8);
+        if global.ty.align() > 1 {
+            p!(ctx, ".align {}", global.ty.align());
+        }
+        p!(ctx, "{}:", global.name);
+        if let Some(bytes) = &global.content {
+            for b in bytes {
+                emit!(ctx, ".byte {}", b);
             }
-            // NOTE: For string termination: '\0'
-            emit!(".byte 0");
         } else {
-            emit!(".zero {}", global.ty.size());
+            emit!(ctx, ".zero {}", global.ty.size());
         }
     }
 
-    p!(".text");
+    p!(ctx, ".text");
     for function in &program.functions {
-        p!(".global {}", function.name);
-        p!("{}:", function.name);
+        ctx.function_name = String::from(function.name);
+        p!(ctx, ".global {}", function.name);
+        p!(ctx, "{}:", function.name);
 
         // 変数分の領域を確保する
-        emit!("push rbp");
-        emit!("mov rbp, rsp");
-        emit!("sub rsp, {}", function.stack_size);
+        emit!(ctx, "push rbp");
+        emit!(ctx, "mov rbp, rsp");
+        emit!(ctx, "sub rsp, {}", function.stack_size);
 
         // 引数をスタックに移動
-        for i in 0..function.params.len() {
-            let param = &function.params[i];
-            match param.ty.size() {
-                1 => {
-                    emit!("mov [rbp-{}], {}", param.offset, ARGREG1[i]);
-                }
-                s => {
-                    assert_eq!(s, 8);
-                    emit!("mov [rbp-{}], {}", param.offset, ARGREG8[i]);
+        for (i, param) in function.params.iter().enumerate() {
+            let reg = match param.ty.size() {
+                1 => ARGREG1[i],
+                2 => ARGREG2[i],
+                4 => ARGREG4[i],
+                8 => ARGREG8[i],
+                _ => {
+                    return Err(Diagnostic::error(format!(
+                        "引数 \"{}\" の型には対応していません",
+                        param.name
+                    )))
                 }
-            }
+            };
+            emit!(ctx, "mov [rbp-{}], {}", param.offset, reg);
         }
 
         for node in &function.nodes {
-            ctx.gen(node);
+            ctx.gen(node)?;
         }
 
         // 式の評価結果としてスタックに一つの値が残っている
         // はずなので、スタックが溢れないようにポップしておく
-        emit!("pop rax");
+        emit!(ctx, "pop rax");
 
         // 最後の式の結果がRAXに残っているのでそれが返り値になる
-        emit!("mov rsp, rbp");
-        emit!("pop rbp");
-        emit!("ret");
+        emit!(ctx, "mov rsp, rbp");
+        emit!(ctx, "pop rbp");
+        emit!(ctx, "ret");
+    }
+
+    // NOTE: スタックが実行可能でないことをリンカに伝える
+    p!(ctx, ".section .note.GNU-stack,\"\",@progbits");
+    return Ok(());
+}
+
+/// アセンブリを out に書き出す
+pub fn codegen(program: &Program, out: &mut dyn Write) -> Result<(), Vec<Diagnostic>> {
+    let mut ctx = CodegenContext::new(out);
+    return gen_program(&mut ctx, program).map_err(|diagnostic| vec![diagnostic]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::tokenizer::tokenize;
+
+    fn compile(input: &str) -> String {
+        let tokens = tokenize(0, input).unwrap();
+        let program = parse(&tokens).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        codegen(&program, &mut out).unwrap();
+        return String::from_utf8(out).unwrap();
+    }
+
+    #[test]
+    fn test_codegen_to_buffer() {
+        let asm = compile("int main() { return 42; }");
+        assert!(asm.starts_with(".intel_syntax noprefix\n"));
+        assert!(asm.contains("main:\n\tpush rbp\n"));
+        assert!(asm.contains("\tpush 42\n\tpop rax\n"));
+
+        // NOTE: 状態を持ち越さないので、何度コンパイルしても同じ結果になる
+        assert_eq!(compile("int main() { return 42; }"), asm);
+    }
+
+    #[test]
+    fn test_codegen_error() {
+        let tokens = tokenize(0, "int main() { 1 = 2; }").unwrap();
+        let program = parse(&tokens).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        let errors = codegen(&program, &mut out).unwrap_err();
+        assert_eq!(errors[0].message, "変数ではありません");
+    }
+
+    #[test]
+    fn test_switch_lowering() {
+        assert!(!is_dense(&[1, 2, 3]));
+        assert!(is_dense(&[1, 2, 3, 5]));
+        assert!(!is_dense(&[1, 10, 100, 1000]));
+        assert!(!is_dense(&[i64::MIN, 0, 1, i64::MAX]));
+
+        let asm = compile(
+            "int main() { switch (2) { case 0: case 1: case 2: case 4: return 1; } return 0; }",
+        );
+        assert!(asm.contains(".section .rodata\n\t.align 8\n.L.jtable.0:\n"));
+        assert!(asm.contains("\t.quad .L.case.0.2\n\t.quad .L.end.0\n\t.quad .L.case.0.3\n"));
+
+        let asm = compile("int main() { switch (2) { case 1: return 1; default: return 0; } }");
+        assert!(!asm.contains(".L.jtable"));
+        assert!(asm.contains("\tje  .L.case.0.0\n\tjmp .L.default.0\n"));
     }
 }
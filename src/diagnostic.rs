@@ -0,0 +1,139 @@
+use crate::source::{FileId, SourceMap};
+use std::fmt;
+use std::string::String;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Severity {
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// ソースコード中の位置
+///
+/// `index` と `len` はバイト単位、`line` と `col` は 1 始まり (col は文字単位)
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Span {
+    pub file_id: FileId,
+    pub line: usize,
+    pub col: usize,
+    pub index: usize,
+    pub len: usize,
+}
+
+#[cfg(test)]
+impl Span {
+    /// 位置を問わないテスト用の Span
+    pub fn dummy() -> Span {
+        return Span {
+            file_id: 0,
+            line: 1,
+            col: 1,
+            index: 0,
+            len: 1,
+        };
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl From<std::io::Error> for Diagnostic {
+    fn from(e: std::io::Error) -> Diagnostic {
+        return Diagnostic::error(format!("出力に失敗しました: {}", e));
+    }
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Diagnostic {
+        return Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+        };
+    }
+
+    pub fn with_span(mut self, span: Span) -> Diagnostic {
+        self.span = Some(span);
+        return self;
+    }
+
+    /// gcc 風に `file:line:col: error: message` と該当行を表示する文字列を組み立てる
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let span = match self.span {
+            Some(span) => span,
+            None => return format!("pcc: {}: {}", self.severity, self.message),
+        };
+        let file = sources.get(span.file_id);
+        let line = file.line_at(span.index);
+
+        // NOTE: タブ幅が揃うように、該当行のタブはそのまま残して他は空白にする
+        let indent: String = line
+            .chars()
+            .take(span.col - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let line_rest = line.chars().count() - (span.col - 1);
+        let width = file.content[span.index..span.index + span.len]
+            .chars()
+            .count();
+        let width = width.min(line_rest).max(1);
+        return format!(
+            "{}:{}:{}: {}: {}\n{}\n{}^{}",
+            file.name,
+            span.line,
+            span.col,
+            self.severity,
+            self.message,
+            line,
+            indent,
+            "~".repeat(width - 1)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(index: usize, len: usize, line: usize, col: usize) -> Span {
+        return Span {
+            file_id: 0,
+            line,
+            col,
+            index,
+            len,
+        };
+    }
+
+    #[test]
+    fn test_render() {
+        let mut sources = SourceMap::new();
+        sources.add("foo.c", String::from("int x;\n\tx = $;\nabc + 1;\n"));
+
+        let diag = Diagnostic::error("だめです");
+        assert_eq!(diag.render(&sources), "pcc: error: だめです");
+
+        let diag = Diagnostic::error("だめです").with_span(span(12, 1, 2, 6));
+        assert_eq!(
+            diag.render(&sources),
+            "foo.c:2:6: error: だめです\n\tx = $;\n\t    ^"
+        );
+
+        let diag = Diagnostic::error("だめです").with_span(span(15, 3, 3, 1));
+        assert_eq!(
+            diag.render(&sources),
+            "foo.c:3:1: error: だめです\nabc + 1;\n^~~"
+        );
+    }
+}
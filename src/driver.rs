@@ -0,0 +1,380 @@
+use crate::diagnostic::Diagnostic;
+use crate::source::FileId;
+use crate::Compiler;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::string::String;
+use std::vec::Vec;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Mode {
+    // "-S"
+    Assembly,
+    // "-c"
+    Object,
+    // 指定なし
+    Executable,
+}
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    // "foo.c"
+    Source(String),
+    // "foo.s"
+    Assembly(String),
+    // "foo.o", "libfoo.a" など
+    Linker(String),
+    // "-"
+    Stdin,
+    // "-e <code>"
+    Code(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    mode: Mode,
+    output: Option<String>,
+    inputs: Vec<Input>,
+    linker_args: Vec<String>,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, Diagnostic> {
+    let mut options = Options {
+        mode: Mode::Executable,
+        output: None,
+        inputs: Vec::new(),
+        linker_args: Vec::new(),
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |flag: &str| {
+            return iter
+                .next()
+                .cloned()
+                .ok_or_else(|| Diagnostic::error(format!("{} の後に引数が必要です", flag)));
+        };
+        match arg.as_str() {
+            "-S" => options.mode = Mode::Assembly,
+            "-c" => options.mode = Mode::Object,
+            "-o" => options.output = Some(value("-o")?),
+            "-e" => options.inputs.push(Input::Code(value("-e")?)),
+            "-" => options.inputs.push(Input::Stdin),
+            _ if arg.starts_with("-o") => options.output = Some(String::from(&arg[2..])),
+            _ if arg.starts_with("-l") || arg.starts_with("-L") || arg.starts_with("-Wl,") => {
+                options.linker_args.push(arg.clone())
+            }
+            // NOTE: プリプロセッサはないため、インクルードパスやマクロの指定は受け付けるだけで何もしない
+            "-I" | "-D" | "-U" => {
+                value(arg)?;
+            }
+            _ if arg.starts_with("-I") || arg.starts_with("-D") || arg.starts_with("-U") => {}
+            // NOTE: 最適化やデバッグ情報、警告、規格の指定は受け付けるだけで何もしない
+            _ if arg.starts_with("-O")
+                || arg.starts_with("-g")
+                || arg.starts_with("-W")
+                || arg.starts_with("-std=") => {}
+            _ if arg.starts_with('-') => {
+                return Err(Diagnostic::error(format!("不明なオプションです: {}", arg)))
+            }
+            _ if arg.ends_with(".c") => options.inputs.push(Input::Source(arg.clone())),
+            _ if arg.ends_with(".s") => options.inputs.push(Input::Assembly(arg.clone())),
+            _ => options.inputs.push(Input::Linker(arg.clone())),
+        }
+    }
+
+    if options.inputs.is_empty() {
+        return Err(Diagnostic::error("入力ファイルがありません"));
+    }
+    let stdin_count = options
+        .inputs
+        .iter()
+        .filter(|input| **input == Input::Stdin)
+        .count();
+    if stdin_count > 1 {
+        return Err(Diagnostic::error("標準入力は一度しか読み込めません"));
+    }
+    if options.output.is_some() && options.mode != Mode::Executable && options.inputs.len() > 1 {
+        return Err(Diagnostic::error(
+            "-S や -c と -o を同時に指定する場合、入力ファイルは一つにしてください",
+        ));
+    }
+    return Ok(options);
+}
+
+/// 一時ファイル名が衝突したときに作り直す回数
+const TEMP_FILE_ATTEMPTS: u32 = 100;
+
+/// 途中生成物の一時ファイルを管理し、終了時に削除する
+struct TempFiles {
+    paths: Vec<PathBuf>,
+    next_id: u32,
+}
+
+impl TempFiles {
+    fn new() -> TempFiles {
+        return TempFiles {
+            paths: Vec::new(),
+            next_id: 0,
+        };
+    }
+
+    /// 空の一時ファイルを作ってそのパスを返す
+    ///
+    /// NOTE: 他人が先に同じ名前のファイルを置いていても使わないよう、新規作成に限って開く
+    fn create(&mut self, extension: &str) -> Result<String, Diagnostic> {
+        for _ in 0..TEMP_FILE_ATTEMPTS {
+            let path = std::env::temp_dir().join(format!(
+                "pcc-{}-{}.{}",
+                std::process::id(),
+                self.next_id,
+                extension
+            ));
+            self.next_id += 1;
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => {
+                    self.paths.push(path.clone());
+                    return Ok(path.to_string_lossy().into_owned());
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(Diagnostic::error(format!(
+                        "一時ファイル {} を作成できません: {}",
+                        path.display(),
+                        e
+                    )))
+                }
+            }
+        }
+        return Err(Diagnostic::error("一時ファイルを作成できません"));
+    }
+}
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn run_command(command: &mut Command) -> Result<(), Diagnostic> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .status()
+        .map_err(|e| Diagnostic::error(format!("{} を実行できません: {}", program, e)))?;
+    if !status.success() {
+        return Err(Diagnostic::error(format!(
+            "{} が失敗しました ({})",
+            program, status
+        )));
+    }
+    return Ok(());
+}
+
+/// 入力ファイル名の拡張子を差し替えた出力ファイル名 (カレントディレクトリに出力する)
+fn replace_extension(path: &str, extension: &str) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .map_or(String::from("a"), |s| s.to_string_lossy().into_owned());
+    return format!("{}.{}", stem, extension);
+}
+
+/// `-S` や `-c` の出力先
+///
+/// `-o` がなければ入力ファイル名から決め、名前のない入力のアセンブリは標準出力に書き出す
+fn output_path(options: &Options, input: &Input, extension: &str) -> Result<String, Diagnostic> {
+    if let Some(output) = &options.output {
+        return Ok(output.clone());
+    }
+    match input {
+        Input::Source(path) | Input::Assembly(path) | Input::Linker(path) => {
+            return Ok(replace_extension(path, extension));
+        }
+        Input::Stdin | Input::Code(_) if extension == "s" => return Ok(String::from("-")),
+        Input::Stdin | Input::Code(_) => {
+            return Err(Diagnostic::error(
+                "標準入力や -e の入力には -o で出力先を指定してください",
+            ));
+        }
+    }
+}
+
+fn read_source(input: &Input, compiler: &mut Compiler) -> Result<FileId, Diagnostic> {
+    match input {
+        Input::Source(path) => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| Diagnostic::error(format!("{} を読み込めません: {}", path, e)))?;
+            return Ok(compiler.add_source(path, content));
+        }
+        Input::Stdin => {
+            let mut content = String::new();
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .map_err(|e| Diagnostic::error(format!("標準入力を読み込めません: {}", e)))?;
+            return Ok(compiler.add_source("<stdin>", content));
+        }
+        Input::Code(code) => return Ok(compiler.add_source("<command-line>", code.as_str())),
+        Input::Assembly(_) | Input::Linker(_) => unreachable!(),
+    }
+}
+
+/// C のソースコードをアセンブリに変換して output に書き出す
+fn compile_to(input: &Input, output: &str, compiler: &mut Compiler) -> Result<(), Vec<Diagnostic>> {
+    let file_id = read_source(input, compiler).map_err(|diagnostic| vec![diagnostic])?;
+    // NOTE: エラー時に書きかけのファイルを残さないよう、一度メモリ上に書き出す
+    let mut asm: Vec<u8> = Vec::new();
+    compiler.compile(file_id, &mut asm)?;
+
+    let result = if output == "-" {
+        std::io::stdout().write_all(&asm)
+    } else {
+        std::fs::write(output, &asm)
+    };
+    return result.map_err(|e| {
+        vec![Diagnostic::error(format!(
+            "{} に書き込めません: {}",
+            output, e
+        ))]
+    });
+}
+
+fn assemble(input: &str, output: &str) -> Result<(), Diagnostic> {
+    return run_command(Command::new("as").arg("-o").arg(output).arg(input));
+}
+
+fn link(inputs: &[String], linker_args: &[String], output: &str) -> Result<(), Diagnostic> {
+    return run_command(
+        Command::new("cc")
+            .arg("-no-pie")
+            .arg("-o")
+            .arg(output)
+            .args(inputs)
+            .args(linker_args),
+    );
+}
+
+/// gcc 互換のコンパイラドライバ
+///
+/// `-S` ならアセンブリ、`-c` ならオブジェクトファイルを出力し、
+/// どちらもなければ `cc` でリンクして実行ファイルを出力する
+pub fn run(args: &[String], compiler: &mut Compiler) -> Result<(), Vec<Diagnostic>> {
+    let options = parse_options(args).map_err(|diagnostic| vec![diagnostic])?;
+    let mut temps = TempFiles::new();
+    let mut objects: Vec<String> = Vec::new();
+
+    for input in &options.inputs {
+        let asm = match input {
+            Input::Linker(path) => {
+                objects.push(path.clone());
+                continue;
+            }
+            Input::Assembly(path) => path.clone(),
+            _ if options.mode == Mode::Assembly => {
+                let output = output_path(&options, input, "s").map_err(|d| vec![d])?;
+                compile_to(input, &output, compiler)?;
+                continue;
+            }
+            _ => {
+                let asm = temps.create("s").map_err(|d| vec![d])?;
+                compile_to(input, &asm, compiler)?;
+                asm
+            }
+        };
+
+        match options.mode {
+            Mode::Assembly => {}
+            Mode::Object => {
+                let output = output_path(&options, input, "o").map_err(|d| vec![d])?;
+                assemble(&asm, &output).map_err(|d| vec![d])?;
+            }
+            Mode::Executable => {
+                let object = temps.create("o").map_err(|d| vec![d])?;
+                assemble(&asm, &object).map_err(|d| vec![d])?;
+                objects.push(object);
+            }
+        }
+    }
+
+    if options.mode == Mode::Executable {
+        let output = options.output.as_deref().unwrap_or("a.out");
+        link(&objects, &options.linker_args, output).map_err(|d| vec![d])?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        return s.split(' ').map(String::from).collect();
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse_options(&args("-c -o out.o -O2 foo.c")).unwrap();
+        assert_eq!(options.mode, Mode::Object);
+        assert_eq!(options.output, Some(String::from("out.o")));
+        assert_eq!(options.inputs, vec![Input::Source(String::from("foo.c"))]);
+
+        let options = parse_options(&args("-oprog main.c util.s lib.o -lm")).unwrap();
+        assert_eq!(options.mode, Mode::Executable);
+        assert_eq!(options.output, Some(String::from("prog")));
+        assert_eq!(
+            options.inputs,
+            vec![
+                Input::Source(String::from("main.c")),
+                Input::Assembly(String::from("util.s")),
+                Input::Linker(String::from("lib.o")),
+            ]
+        );
+        assert_eq!(options.linker_args, vec![String::from("-lm")]);
+
+        let options = parse_options(&args(
+            "-Iinc -D FOO -DBAR=1 -std=c11 -Wall -Wl,-rpath,/opt a.c",
+        ))
+        .unwrap();
+        assert_eq!(options.inputs, vec![Input::Source(String::from("a.c"))]);
+        assert_eq!(options.linker_args, vec![String::from("-Wl,-rpath,/opt")]);
+
+        assert!(parse_options(&args("-S")).is_err());
+        assert!(parse_options(&args("-S -o x.s a.c b.c")).is_err());
+        assert!(parse_options(&args("- -")).is_err());
+        assert!(parse_options(&args("-x c foo.c")).is_err());
+    }
+
+    #[test]
+    fn test_output_path() {
+        let options = parse_options(&args("-S dir/foo.c")).unwrap();
+        let path = output_path(&options, &options.inputs[0], "s").unwrap();
+        assert_eq!(path, "foo.s");
+
+        let options = parse_options(&args("-S -")).unwrap();
+        let path = output_path(&options, &options.inputs[0], "s").unwrap();
+        assert_eq!(path, "-");
+
+        let options = parse_options(&args("-c -")).unwrap();
+        assert!(output_path(&options, &options.inputs[0], "o").is_err());
+    }
+
+    #[test]
+    fn test_temp_files() {
+        let mut temps = TempFiles::new();
+        let a = temps.create("s").unwrap();
+        let b = temps.create("s").unwrap();
+        assert_ne!(a, b);
+        assert!(Path::new(&a).exists() && Path::new(&b).exists());
+
+        // NOTE: 既に存在する名前は飛ばして作り直す
+        let mut other = TempFiles::new();
+        let c = other.create("s").unwrap();
+        assert!(c != a && c != b);
+
+        drop(temps);
+        assert!(!Path::new(&a).exists() && !Path::new(&b).exists());
+        assert!(Path::new(&c).exists());
+    }
+}
@@ -0,0 +1,95 @@
+//! C コンパイラ pcc のライブラリ
+//!
+//! ```
+//! use pcc::{CompileOptions, Compiler};
+//!
+//! let mut compiler = Compiler::new(CompileOptions::default());
+//! let file_id = compiler.add_source("main.c", "int main() { return 0; }");
+//! let asm = compiler.compile_to_string(file_id).unwrap();
+//! assert!(asm.contains("main:"));
+//! ```
+#![allow(clippy::needless_return)]
+
+pub mod codegen;
+pub mod diagnostic;
+pub mod driver;
+pub mod parser;
+pub mod source;
+pub mod tokenizer;
+
+use diagnostic::Diagnostic;
+use source::{FileId, SourceMap};
+use std::io::Write;
+use std::string::String;
+use std::vec::Vec;
+
+/// コンパイルの設定
+///
+/// NOTE: 今のところ設定項目はないが、項目を増やしても呼び出し側が壊れないよう
+/// `CompileOptions::default()` から作ること
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {}
+
+/// ソースコードを受け取り、アセンブリか診断メッセージを返す
+#[derive(Debug, Default)]
+pub struct Compiler {
+    options: CompileOptions,
+    sources: SourceMap,
+}
+
+impl Compiler {
+    pub fn new(options: CompileOptions) -> Compiler {
+        return Compiler {
+            options,
+            sources: SourceMap::new(),
+        };
+    }
+
+    pub fn options(&self) -> &CompileOptions {
+        return &self.options;
+    }
+
+    pub fn sources(&self) -> &SourceMap {
+        return &self.sources;
+    }
+
+    /// name は診断メッセージに表示されるファイル名
+    pub fn add_source(&mut self, name: &str, content: impl Into<String>) -> FileId {
+        return self.sources.add(name, content.into());
+    }
+
+    /// tokenize -> parse -> codegen を通してアセンブリを out に書き出す
+    pub fn compile(&self, file_id: FileId, out: &mut dyn Write) -> Result<(), Vec<Diagnostic>> {
+        let tokens = tokenizer::tokenize(file_id, &self.sources.get(file_id).content)?;
+        let program = parser::parse(&tokens)?;
+        return codegen::codegen(&program, out);
+    }
+
+    pub fn compile_to_string(&self, file_id: FileId) -> Result<String, Vec<Diagnostic>> {
+        let mut out: Vec<u8> = Vec::new();
+        self.compile(file_id, &mut out)?;
+        return Ok(String::from_utf8(out).expect("アセンブリは UTF-8 で出力される"));
+    }
+
+    /// 診断メッセージを `file:line:col: error: ...` の形式で表示用の文字列にする
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        return diagnostic.render(&self.sources);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_error() {
+        let mut compiler = Compiler::new(CompileOptions::default());
+        let file_id = compiler.add_source("foo.c", "int main() {\n  return x;\n}\n");
+        let errors = compiler.compile_to_string(file_id).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            compiler.render(&errors[0]),
+            "foo.c:2:10: error: 未定義の変数 \"x\" を参照しました。\n  return x;\n         ^"
+        );
+    }
+}
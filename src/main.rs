@@ -1,22 +1,15 @@
-mod codegen;
-mod parser;
-mod tokenizer;
-
-use codegen::codegen;
-use parser::parse;
+use pcc::driver;
+use pcc::Compiler;
 use std::vec::Vec;
-use tokenizer::tokenize;
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        panic!("引数の個数が正しくありません");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut compiler = Compiler::default();
+    if let Err(diagnostics) = driver::run(&args, &mut compiler) {
+        for diagnostic in &diagnostics {
+            eprintln!("{}", compiler.render(diagnostic));
+        }
+        std::process::exit(1);
     }
-
-    let input: &String = &args[1];
-    let tokens = tokenize(&input);
-    let program = parse(&tokens);
-
-    codegen(&program);
     std::process::exit(0);
 }
@@ -1,459 +1,2122 @@
 use super::global_holder::GlobalHolder;
-use super::token_cursor::TokenCursor;
+use super::local_holder::LocalHolder;
+use super::token_cursor::{ParseResult, TokenCursor};
 use super::types::*;
-use crate::tokenizer::Keyword;
+use crate::diagnostic::{Diagnostic, Span};
+use crate::tokenizer::{IntLiteral, Keyword, Token, TokenKind};
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::rc::Rc;
 use std::string::String;
 use std::vec::Vec;
 
-fn detect_type(kind: &NodeKind) -> Option<Type> {
-    match kind {
-        NodeKind::Mul { .. }
-        | NodeKind::Div { .. }
-        | NodeKind::Equal { .. }
+/// 整数拡張
+fn integer_promoted(ty: &Type) -> Type {
+    match ty {
+        Type::Bool | Type::Char | Type::UChar | Type::Short | Type::UShort | Type::Enum => {
+            return Type::Int
+        }
+        _ => return ty.clone(),
+    }
+}
+
+fn integer_rank(ty: &Type) -> u32 {
+    match ty {
+        Type::Bool => return 0,
+        Type::Char | Type::UChar => return 1,
+        Type::Short | Type::UShort => return 2,
+        Type::Int | Type::UInt | Type::Enum => return 3,
+        _ => return 4,
+    }
+}
+
+/// 通常の算術型変換を行った後の共通の型 (C11 6.3.1.8)
+fn usual_arith_type(lhs: &Type, rhs: &Type) -> Type {
+    let lhs = integer_promoted(lhs);
+    let rhs = integer_promoted(rhs);
+    if lhs == rhs {
+        return lhs;
+    }
+    let (signed, unsigned) = match (lhs.is_unsigned(), rhs.is_unsigned()) {
+        (false, false) | (true, true) => {
+            let higher = if integer_rank(&lhs) >= integer_rank(&rhs) {
+                lhs
+            } else {
+                rhs
+            };
+            return higher;
+        }
+        (false, true) => (lhs, rhs),
+        (true, false) => (rhs, lhs),
+    };
+    if integer_rank(&unsigned) >= integer_rank(&signed) {
+        return unsigned;
+    }
+    if signed.max_value() >= unsigned.max_value() {
+        return signed;
+    }
+    match signed {
+        Type::Int => return Type::UInt,
+        _ => return Type::ULong,
+    }
+}
+
+/// 両辺の型から算術演算の結果の型を決める
+fn arith_type(lhs: &Node, rhs: &Node) -> Option<Type> {
+    match (&lhs.ty, &rhs.ty) {
+        (Some(l), Some(r)) => return Some(usual_arith_type(l, r)),
+        _ => return Some(Type::Int),
+    }
+}
+
+/// 整数型を表す型指定子
+const INTEGER_SPECIFIERS: &[Keyword] = &[
+    Keyword::Bool,
+    Keyword::Char,
+    Keyword::Short,
+    Keyword::Int,
+    Keyword::Long,
+    Keyword::Signed,
+    Keyword::Unsigned,
+];
+
+const ASSIGN_OPS: &[(&str, BinaryOp)] = &[
+    ("+=", BinaryOp::Add),
+    ("-=", BinaryOp::Sub),
+    ("*=", BinaryOp::Mul),
+    ("/=", BinaryOp::Div),
+    ("%=", BinaryOp::Mod),
+    ("<<=", BinaryOp::Shl),
+    (">>=", BinaryOp::Shr),
+    ("&=", BinaryOp::BitAnd),
+    ("|=", BinaryOp::BitOr),
+    ("^=", BinaryOp::BitXor),
+];
+
+/// 整数型 (型が決まらないものを含む) の値か
+fn is_integer_node(node: &Node) -> bool {
+    match &node.ty {
+        Some(ty) => return ty.is_integer(),
+        None => return true,
+    }
+}
+
+fn integer_operand_error(span: Span) -> Diagnostic {
+    return Diagnostic::error("整数型の値が必要です").with_span(span);
+}
+
+/// 二項演算の結果の型を決める
+fn binary_type(op: BinaryOp, lhs: &Node, rhs: &Node, span: Span) -> ParseResult<Option<Type>> {
+    let error = |msg: &str| Err(Diagnostic::error(msg).with_span(span));
+    // NOTE: ポインタを使えるのは加減算だけ
+    if !matches!(op, BinaryOp::Add | BinaryOp::Sub) {
+        if let Some(node) = [lhs, rhs].iter().find(|node| !is_integer_node(node)) {
+            return Err(integer_operand_error(node.span));
+        }
+    }
+    let ty = match op {
+        BinaryOp::Mul
+        | BinaryOp::Div
+        | BinaryOp::Mod
+        | BinaryOp::BitAnd
+        | BinaryOp::BitOr
+        | BinaryOp::BitXor => arith_type(lhs, rhs),
+        BinaryOp::Shl | BinaryOp::Shr => lhs.ty.as_ref().map(integer_promoted),
+        BinaryOp::Add => match (&lhs.ty, &rhs.ty) {
+            (_, Some(Type::Pointer(_))) => return error("ポインタを加算の右辺値に指定できません"),
+            (_, Some(Type::Array(..))) => return error("配列を加算の右辺値に指定できません"),
+            (Some(ty @ Type::Pointer(_)), _) | (Some(ty @ Type::Array(..)), _) => Some(decayed(ty)),
+            _ => arith_type(lhs, rhs),
+        },
+        BinaryOp::Sub => match (&lhs.ty, &rhs.ty) {
+            (_, Some(Type::Pointer(_))) => return error("ポインタを減算の右辺値に指定できません"),
+            (_, Some(Type::Array(..))) => return error("配列を減算の右辺値に指定できません"),
+            (Some(ty @ Type::Pointer(_)), _) | (Some(ty @ Type::Array(..)), _) => Some(decayed(ty)),
+            _ => arith_type(lhs, rhs),
+        },
+    };
+    return Ok(ty);
+}
+
+/// 配列型をポインタ型に変換する
+fn decayed(ty: &Type) -> Type {
+    match ty {
+        Type::Array(base, _) => return Type::Pointer(base.clone()),
+        _ => return ty.clone(),
+    }
+}
+
+/// 値が 0 の整数定数式
+fn is_null_pointer_constant(node: &Node) -> bool {
+    match &node.ty {
+        Some(ty) if ty.is_integer() => return eval_const(node) == Some(0),
+        _ => return false,
+    }
+}
+
+/// 条件演算子の結果の型を決める
+fn cond_type(then: &Node, els: &Node, span: Span) -> ParseResult<Option<Type>> {
+    let (then_ty, els_ty) = match (&then.ty, &els.ty) {
+        (Some(then_ty), Some(els_ty)) => (decayed(then_ty), decayed(els_ty)),
+        _ => return Ok(None),
+    };
+    match (&then_ty, &els_ty) {
+        (Type::Pointer(then_base), Type::Pointer(els_base)) => {
+            // NOTE: 片方が void* ならもう片方を void* に変換する
+            if let Type::Void = **then_base {
+                return Ok(Some(then_ty));
+            }
+            if let Type::Void = **els_base {
+                return Ok(Some(els_ty));
+            }
+            if then_ty != els_ty {
+                return Err(
+                    Diagnostic::error("条件演算子の両辺のポインタの型が異なります").with_span(span),
+                );
+            }
+            return Ok(Some(then_ty));
+        }
+        (Type::Pointer(_), _) if is_null_pointer_constant(els) => return Ok(Some(then_ty)),
+        (_, Type::Pointer(_)) if is_null_pointer_constant(then) => return Ok(Some(els_ty)),
+        (Type::Pointer(_), _) | (_, Type::Pointer(_)) => {
+            return Err(
+                Diagnostic::error("条件演算子の両辺にポインタと整数が混在しています")
+                    .with_span(span),
+            );
+        }
+        (Type::Struct(_), _) | (_, Type::Struct(_)) => {
+            if then_ty != els_ty {
+                return Err(
+                    Diagnostic::error("条件演算子の両辺の構造体の型が異なります").with_span(span),
+                );
+            }
+            return Ok(Some(then_ty));
+        }
+        _ => return Ok(Some(usual_arith_type(&then_ty, &els_ty))),
+    }
+}
+
+fn detect_type(kind: &NodeKind, span: Span) -> ParseResult<Option<Type>> {
+    let error = |msg: &str| Err(Diagnostic::error(msg).with_span(span));
+    let ty = match kind {
+        NodeKind::Equal { .. }
         | NodeKind::NotEqual { .. }
         | NodeKind::LessThan { .. }
         | NodeKind::LessThanEqual { .. }
-        | NodeKind::FunCall { .. }
+        | NodeKind::LogAnd { .. }
+        | NodeKind::LogOr { .. }
+        | NodeKind::Not(_)
         | NodeKind::Number(_) => Some(Type::Int),
-        NodeKind::Add { lhs, rhs } => match rhs.ty {
-            Some(Type::Pointer(_)) => panic!("ポインタを加算の右辺値に指定できません"),
-            Some(Type::Array(..)) => panic!("配列を加算の右辺値に指定できません"),
-            _ => lhs.ty.clone(),
-        },
-        NodeKind::Sub { lhs, rhs } => match rhs.ty {
-            Some(Type::Pointer(_)) => panic!("ポインタを減算の右辺値に指定できません"),
-            Some(Type::Array(..)) => panic!("配列を減算の右辺値に指定できません"),
+        NodeKind::Add { lhs, rhs } => return binary_type(BinaryOp::Add, lhs, rhs, span),
+        NodeKind::Sub { lhs, rhs } => return binary_type(BinaryOp::Sub, lhs, rhs, span),
+        NodeKind::Mul { lhs, rhs } => return binary_type(BinaryOp::Mul, lhs, rhs, span),
+        NodeKind::Div { lhs, rhs } => return binary_type(BinaryOp::Div, lhs, rhs, span),
+        NodeKind::Mod { lhs, rhs } => return binary_type(BinaryOp::Mod, lhs, rhs, span),
+        NodeKind::Shl { lhs, rhs } => return binary_type(BinaryOp::Shl, lhs, rhs, span),
+        NodeKind::Shr { lhs, rhs } => return binary_type(BinaryOp::Shr, lhs, rhs, span),
+        NodeKind::BitAnd { lhs, rhs } => return binary_type(BinaryOp::BitAnd, lhs, rhs, span),
+        NodeKind::BitOr { lhs, rhs } => return binary_type(BinaryOp::BitOr, lhs, rhs, span),
+        NodeKind::BitXor { lhs, rhs } => return binary_type(BinaryOp::BitXor, lhs, rhs, span),
+        NodeKind::BitNot(target) if !is_integer_node(target) => {
+            return Err(integer_operand_error(target.span))
+        }
+        NodeKind::BitNot(target) => target.ty.as_ref().map(integer_promoted),
+        NodeKind::Variable(var) => Some(var.ty.clone()),
+        NodeKind::Cond { then, els, .. } => return cond_type(then, els, span),
+        NodeKind::Comma { rhs, .. } => rhs.ty.as_ref().map(decayed),
+        NodeKind::Assign { lhs, rhs } => match (&lhs.ty, &rhs.ty) {
+            (Some(Type::Struct(_)), _) | (_, Some(Type::Struct(_))) if lhs.ty != rhs.ty => {
+                return error("代入する構造体の型が異なります")
+            }
+            (Some(ty), _) if !is_assignable(ty, rhs) => return error("代入する値の型が異なります"),
             _ => lhs.ty.clone(),
         },
-        NodeKind::Variable(var) => Some((*var).ty.clone()),
-        NodeKind::Assign { lhs, .. } => lhs.ty.clone(),
+        NodeKind::AssignOp { lhs, .. } | NodeKind::PostInc(lhs) | NodeKind::PostDec(lhs) => {
+            lhs.ty.clone()
+        }
         NodeKind::Addr(target) => match &target.ty {
-            Some(Type::Array(base, ..)) => Some(Type::Pointer(Box::new(*base.clone()))),
+            Some(Type::Array(base, ..)) => Some(Type::Pointer(base.clone())),
             Some(ty) => Some(Type::Pointer(Box::new(ty.clone()))),
-            _ => panic!("アドレス参照先の型が不明です"),
+            _ => return error("アドレス参照先の型が不明です"),
         },
         NodeKind::Deref(target) => match &target.ty {
             Some(Type::Pointer(base)) | Some(Type::Array(base, _)) => Some(*base.clone()),
-            _ => panic!("デリファレンスできない型です"),
+            _ => return error("デリファレンスできない型です"),
         },
+        NodeKind::Member { member, .. } => Some(member.ty.clone()),
         _ => None,
+    };
+    return Ok(ty);
+}
+
+/// 整数型どうしで型が異なれば、node を ty への Cast ノードで包む
+fn convert(node: &mut Box<Node>, ty: &Type) {
+    match &node.ty {
+        Some(from) if from != ty && from.is_integer() && ty.is_integer() => {}
+        Some(Type::Pointer(_)) | Some(Type::Array(..)) if *ty == Type::Bool => {}
+        _ => return,
     }
+    let span = node.span;
+    let target = std::mem::replace(
+        node,
+        Box::new(Node {
+            kind: NodeKind::Null,
+            ty: None,
+            span,
+        }),
+    );
+    **node = Node {
+        kind: NodeKind::Cast(target),
+        ty: Some(ty.clone()),
+        span,
+    };
 }
 
-fn make_node<'a>(mut kind: NodeKind<'a>) -> Node<'a> {
-    match &mut kind {
-        // NOTE: 加算の右辺値がポインタ型や配列型の場合は左辺値と入れ替える
-        NodeKind::Add { lhs, rhs } => match rhs.ty {
-            Some(Type::Pointer(_)) | Some(Type::Array(..)) => std::mem::swap(lhs, rhs),
-            _ => {}
+/// 整数拡張の Cast ノードを挟む
+fn promote(node: &mut Box<Node>) {
+    if let Some(ty) = node.ty.as_ref().map(integer_promoted) {
+        convert(node, &ty);
+    }
+}
+
+/// 整数拡張と通常の算術型変換、代入による型変換の Cast ノードを挟む
+fn insert_conversions(kind: &mut NodeKind) {
+    match kind {
+        NodeKind::Add { lhs, rhs }
+        | NodeKind::Sub { lhs, rhs }
+        | NodeKind::Mul { lhs, rhs }
+        | NodeKind::Div { lhs, rhs }
+        | NodeKind::Mod { lhs, rhs }
+        | NodeKind::BitAnd { lhs, rhs }
+        | NodeKind::BitOr { lhs, rhs }
+        | NodeKind::BitXor { lhs, rhs }
+        | NodeKind::Equal { lhs, rhs }
+        | NodeKind::NotEqual { lhs, rhs }
+        | NodeKind::LessThan { lhs, rhs }
+        | NodeKind::LessThanEqual { lhs, rhs }
+        | NodeKind::Cond {
+            then: lhs,
+            els: rhs,
+            ..
+        } => {
+            let ty = match (&lhs.ty, &rhs.ty) {
+                (Some(l), Some(r)) if l.is_integer() && r.is_integer() => usual_arith_type(l, r),
+                _ => return,
+            };
+            convert(lhs, &ty);
+            convert(rhs, &ty);
+        }
+        NodeKind::Shl { lhs, rhs } | NodeKind::Shr { lhs, rhs } => {
+            promote(lhs);
+            promote(rhs);
+        }
+        NodeKind::BitNot(target) => promote(target),
+        NodeKind::Assign { lhs, rhs } => {
+            if let Some(ty) = &lhs.ty {
+                convert(rhs, ty);
+            }
+        }
+        // NOTE: 左辺の変換はコード生成時に行う
+        NodeKind::AssignOp {
+            op,
+            rhs,
+            op_ty: Some(op_ty),
+            ..
+        } => match op {
+            BinaryOp::Shl | BinaryOp::Shr => promote(rhs),
+            _ => convert(rhs, op_ty),
         },
         _ => {}
     }
-    let ty = detect_type(&kind);
-    return Node { kind, ty };
 }
 
-fn align_to(n: u32, align: u32) -> u32 {
-    return (n + align - 1) & !(align - 1);
+/// 整数の値を ty の型で表せる値に変換する
+fn truncated(value: i64, ty: &Type) -> i64 {
+    if let Type::Bool = ty {
+        return (value != 0) as i64;
+    }
+    match (ty.size(), ty.is_unsigned()) {
+        (1, false) => return i64::from(value as i8),
+        (1, true) => return i64::from(value as u8),
+        (2, false) => return i64::from(value as i16),
+        (2, true) => return i64::from(value as u16),
+        (4, false) => return i64::from(value as i32),
+        (4, true) => return i64::from(value as u32),
+        _ => return value,
+    }
 }
 
-struct LocalHolder<'a> {
-    locals: Vec<Rc<Variable<'a>>>,
-}
-impl<'a> LocalHolder<'a> {
-    pub fn new() -> LocalHolder<'a> {
-        return LocalHolder { locals: Vec::new() };
+/// 整数定数式を評価する
+///
+/// 定数式でなければ `None` を返す
+fn eval_const(node: &Node) -> Option<i64> {
+    let unsigned = matches!(&node.ty, Some(ty) if ty.is_unsigned());
+    let value = match &node.kind {
+        NodeKind::Number(n) => *n,
+        NodeKind::Cast(target) => eval_const(target)?,
+        NodeKind::Add { lhs, rhs } => eval_const(lhs)?.wrapping_add(eval_const(rhs)?),
+        NodeKind::Sub { lhs, rhs } => eval_const(lhs)?.wrapping_sub(eval_const(rhs)?),
+        NodeKind::Mul { lhs, rhs } => eval_const(lhs)?.wrapping_mul(eval_const(rhs)?),
+        NodeKind::Div { lhs, rhs } | NodeKind::Mod { lhs, rhs } => {
+            let (lhs, rhs) = (eval_const(lhs)?, eval_const(rhs)?);
+            if rhs == 0 {
+                return None;
+            }
+            match (&node.kind, unsigned) {
+                (NodeKind::Div { .. }, true) => ((lhs as u64) / (rhs as u64)) as i64,
+                (NodeKind::Div { .. }, false) => lhs.wrapping_div(rhs),
+                (_, true) => ((lhs as u64) % (rhs as u64)) as i64,
+                (_, false) => lhs.wrapping_rem(rhs),
+            }
+        }
+        NodeKind::Shl { lhs, rhs } => eval_const(lhs)?.wrapping_shl(eval_const(rhs)? as u32),
+        NodeKind::Shr { lhs, rhs } => {
+            let (lhs, rhs) = (eval_const(lhs)?, eval_const(rhs)? as u32);
+            if unsigned {
+                (lhs as u64).wrapping_shr(rhs) as i64
+            } else {
+                lhs.wrapping_shr(rhs)
+            }
+        }
+        NodeKind::BitAnd { lhs, rhs } => eval_const(lhs)? & eval_const(rhs)?,
+        NodeKind::BitOr { lhs, rhs } => eval_const(lhs)? | eval_const(rhs)?,
+        NodeKind::BitXor { lhs, rhs } => eval_const(lhs)? ^ eval_const(rhs)?,
+        NodeKind::BitNot(target) => !eval_const(target)?,
+        NodeKind::Not(target) => (eval_const(target)? == 0) as i64,
+        NodeKind::Equal { lhs, rhs } => (eval_const(lhs)? == eval_const(rhs)?) as i64,
+        NodeKind::NotEqual { lhs, rhs } => (eval_const(lhs)? != eval_const(rhs)?) as i64,
+        NodeKind::LessThan { lhs, rhs } | NodeKind::LessThanEqual { lhs, rhs } => {
+            let unsigned = matches!(&lhs.ty, Some(ty) if ty.is_unsigned());
+            let (lhs, rhs) = (eval_const(lhs)?, eval_const(rhs)?);
+            match (&node.kind, unsigned) {
+                (NodeKind::LessThan { .. }, true) => ((lhs as u64) < (rhs as u64)) as i64,
+                (NodeKind::LessThan { .. }, false) => (lhs < rhs) as i64,
+                (_, true) => ((lhs as u64) <= (rhs as u64)) as i64,
+                (_, false) => (lhs <= rhs) as i64,
+            }
+        }
+        NodeKind::LogAnd { lhs, rhs } => (eval_const(lhs)? != 0 && eval_const(rhs)? != 0) as i64,
+        NodeKind::LogOr { lhs, rhs } => (eval_const(lhs)? != 0 || eval_const(rhs)? != 0) as i64,
+        NodeKind::Cond { cond, then, els } => {
+            if eval_const(cond)? != 0 {
+                eval_const(then)?
+            } else {
+                eval_const(els)?
+            }
+        }
+        _ => return None,
+    };
+    match &node.ty {
+        Some(ty) if ty.is_integer() => return Some(truncated(value, ty)),
+        _ => return Some(value),
     }
+}
 
-    fn total_variable_size(&self) -> u32 {
-        return self.locals.iter().fold(0, |acc, v| acc + v.ty.size());
+/// 引数の受け渡しのように、node の値を暗黙に ty の型に変換できるかどうか
+fn is_assignable(ty: &Type, node: &Node) -> bool {
+    let from = match &node.ty {
+        Some(from) => decayed(from),
+        None => return true,
+    };
+    match (ty, &from) {
+        _ if ty.is_integer() && from.is_integer() => return true,
+        (Type::Bool, Type::Pointer(_)) => return true,
+        (Type::Pointer(_), _) if is_null_pointer_constant(node) => return true,
+        // NOTE: void* は他のオブジェクトへのポインタと相互に変換できる
+        (Type::Pointer(base), Type::Pointer(from_base)) => {
+            return base == from_base || **base == Type::Void || **from_base == Type::Void
+        }
+        _ => return *ty == from,
     }
+}
 
-    pub fn stack_size(&self) -> u32 {
-        return align_to(self.total_variable_size(), 8);
+/// 値として使われる被演算子
+fn value_operands<'k, 'a>(kind: &'k NodeKind<'a>) -> Vec<&'k Node<'a>> {
+    match kind {
+        NodeKind::Add { lhs, rhs }
+        | NodeKind::Sub { lhs, rhs }
+        | NodeKind::Mul { lhs, rhs }
+        | NodeKind::Div { lhs, rhs }
+        | NodeKind::Mod { lhs, rhs }
+        | NodeKind::Shl { lhs, rhs }
+        | NodeKind::Shr { lhs, rhs }
+        | NodeKind::Equal { lhs, rhs }
+        | NodeKind::NotEqual { lhs, rhs }
+        | NodeKind::LessThan { lhs, rhs }
+        | NodeKind::LessThanEqual { lhs, rhs }
+        | NodeKind::BitAnd { lhs, rhs }
+        | NodeKind::BitOr { lhs, rhs }
+        | NodeKind::BitXor { lhs, rhs }
+        | NodeKind::LogAnd { lhs, rhs }
+        | NodeKind::LogOr { lhs, rhs }
+        | NodeKind::Assign { lhs, rhs }
+        | NodeKind::AssignOp { lhs, rhs, .. } => return vec![lhs, rhs],
+        NodeKind::BitNot(target)
+        | NodeKind::Not(target)
+        | NodeKind::PostInc(target)
+        | NodeKind::PostDec(target)
+        | NodeKind::Deref(target)
+        | NodeKind::Member { target, .. } => return vec![target],
+        // NOTE: 条件演算子は両辺がともに void 型なら値として使わない
+        NodeKind::Cond { cond, then, els } => match (&then.ty, &els.ty) {
+            (Some(Type::Void), Some(Type::Void)) => return vec![cond],
+            _ => return vec![cond, then, els],
+        },
+        NodeKind::If { cond, .. }
+        | NodeKind::While { cond, .. }
+        | NodeKind::DoWhile { cond, .. }
+        | NodeKind::Switch { cond, .. } => return vec![cond],
+        NodeKind::For {
+            cond: Some(cond), ..
+        } => return vec![cond],
+        NodeKind::FunCall { args, .. } => return args.iter().collect(),
+        _ => return Vec::new(),
     }
+}
 
-    pub fn new_var(&mut self, name: &'a str, ty: Type) -> Rc<Variable<'a>> {
-        let var = Rc::new(Variable {
-            name: String::from(name),
-            offset: self.total_variable_size() + ty.size(),
-            ty,
-            is_local: true,
-            content: None,
-        });
-        self.locals.push(var.clone());
-        return var;
+/// value_operands のうち、スカラー型 (整数やポインタ) でなければならない被演算子
+fn scalar_operands<'k, 'a>(kind: &'k NodeKind<'a>) -> Vec<&'k Node<'a>> {
+    match kind {
+        // NOTE: 構造体は代入やメンバアクセス、条件演算子の両辺には使える
+        NodeKind::Assign { .. } | NodeKind::Member { .. } | NodeKind::FunCall { .. } => {
+            return Vec::new()
+        }
+        NodeKind::Cond { cond, .. } => return vec![cond],
+        _ => return value_operands(kind),
     }
+}
 
-    pub fn find(&self, name: &str) -> Option<Rc<Variable<'a>>> {
-        for var in &self.locals {
-            if var.name == name {
-                return Some(var.clone());
-            }
+fn void_value_error(span: Span) -> Diagnostic {
+    return Diagnostic::error("void 型の値は使用できません").with_span(span);
+}
+
+fn make_node<'a>(mut kind: NodeKind<'a>, span: Span) -> ParseResult<Node<'a>> {
+    if let Some(node) = value_operands(&kind)
+        .into_iter()
+        .find(|node| node.ty == Some(Type::Void))
+    {
+        return Err(void_value_error(node.span));
+    }
+    if let Some(node) = scalar_operands(&kind)
+        .into_iter()
+        .find(|node| matches!(node.ty, Some(Type::Struct(_))))
+    {
+        return Err(
+            Diagnostic::error("構造体の値はこの演算には使用できません").with_span(node.span)
+        );
+    }
+    // NOTE: 加算の右辺値がポインタ型や配列型の場合は左辺値と入れ替える
+    if let NodeKind::Add { lhs, rhs } = &mut kind {
+        if let Some(Type::Pointer(_)) | Some(Type::Array(..)) = rhs.ty {
+            std::mem::swap(lhs, rhs);
         }
-        return None;
     }
+    insert_conversions(&mut kind);
+    let ty = detect_type(&kind, span)?;
+    return Ok(Node { kind, ty, span });
+}
 
-    pub fn dump_to_vec(self) -> Vec<Rc<Variable<'a>>> {
-        return self.locals;
+/// lengths の要素数を外側から順に持つ配列型
+fn array_of(ty: Type, lengths: &[u32], span: Span) -> ParseResult<Type> {
+    let mut ty = ty;
+    for length in lengths.iter().rev() {
+        ty = Type::array_of(ty, *length)
+            .ok_or_else(|| Diagnostic::error("配列が大きすぎます").with_span(span))?;
     }
+    return Ok(ty);
+}
+
+/// 整数リテラルの型を C11 6.4.4.1 の規則で決める
+///
+/// NOTE: long long は long と同じ型として扱う
+fn int_literal_type(literal: &IntLiteral) -> Option<Type> {
+    let candidates: &[Type] = match (literal.unsigned, literal.long, literal.decimal) {
+        (false, false, true) => &[Type::Int, Type::Long],
+        (false, false, false) => &[Type::Int, Type::UInt, Type::Long, Type::ULong],
+        (true, false, _) => &[Type::UInt, Type::ULong],
+        (false, true, true) => &[Type::Long],
+        (false, true, false) => &[Type::Long, Type::ULong],
+        (true, true, _) => &[Type::ULong],
+    };
+    return candidates
+        .iter()
+        .find(|ty| literal.value <= ty.max_value())
+        .cloned();
+}
+
+fn tag_kind_error(tag: &str, span: Span) -> Diagnostic {
+    return Diagnostic::error(format!(
+        "\"{}\" は別の種類のタグとして宣言されています",
+        tag
+    ))
+    .with_span(span);
+}
+
+/// レジスタで受け渡せる引数の数
+const MAX_ARGS: usize = 6;
+
+fn too_many_args_error(span: Span) -> Diagnostic {
+    return Diagnostic::error(format!("{} 個以上の引数には対応していません", MAX_ARGS + 1))
+        .with_span(span);
+}
+
+/// 関数の引数名 (プロトタイプ宣言では省略できる) と型、位置
+type Param<'a> = (Option<&'a str>, Type, Span);
+
+/// switch 文の本体に現れた case と default
+struct SwitchContext {
+    // 整数拡張した条件式の型で、case の値はこの型に変換して持つ
+    ty: Type,
+    cases: Vec<i64>,
+    has_default: bool,
 }
 
 pub struct FunctionParser<'local, 'outer: 'local> {
-    globals: &'local mut GlobalHolder<'outer>,
-    locals: LocalHolder<'outer>,
+    globals: &'local mut GlobalHolder,
+    locals: LocalHolder,
     cursor: &'local mut TokenCursor<'outer>,
+    // break できる文 (ループ) の入れ子の深さ
+    break_depth: u32,
+    // continue できる文 (ループ) の入れ子の深さ
+    continue_depth: u32,
+    // 関数内で定義されたラベル
+    labels: HashMap<&'outer str, Span>,
+    // goto で参照されたラベル
+    gotos: Vec<(&'outer str, Span)>,
+    // 解析中の switch 文で、最も内側のものが末尾になる
+    switches: Vec<SwitchContext>,
+    // 解析中の関数の戻り値の型
+    return_ty: Option<Type>,
 }
 impl<'local, 'outer: 'local> FunctionParser<'local, 'outer> {
     fn new(
         cursor: &'local mut TokenCursor<'outer>,
-        globals: &'local mut GlobalHolder<'outer>,
+        globals: &'local mut GlobalHolder,
     ) -> FunctionParser<'local, 'outer> {
         return FunctionParser {
             locals: LocalHolder::new(),
             cursor,
             globals,
+            break_depth: 0,
+            continue_depth: 0,
+            labels: HashMap::new(),
+            gotos: Vec::new(),
+            switches: Vec::new(),
+            return_ty: None,
         };
     }
 
-    fn find_var(&self, name: &str) -> Option<Rc<Variable<'outer>>> {
+    fn find_symbol(&self, name: &str) -> Option<Symbol> {
         return self.locals.find(name).or_else(|| {
-            return self.globals.find_var(name);
+            return self.globals.find(name);
         });
     }
 
-    fn read_func_params(&mut self) -> Vec<Rc<Variable<'outer>>> {
-        let mut params = Vec::new();
-        if self.cursor.consume_sign(")") {
-            return params;
+    fn push_enum_const(&mut self, name: &str, value: i64, span: Span) -> ParseResult<()> {
+        if self.locals.in_function() {
+            return self.locals.push_const(name, value, span);
+        }
+        return self.globals.push_const(name, value, span);
+    }
+
+    fn find_tag(&self, name: &str) -> Option<Type> {
+        return self.locals.find_tag(name).or_else(|| {
+            return self.globals.find_tag(name);
+        });
+    }
+
+    /// 現在のスコープで宣言されたタグだけを探す
+    fn find_tag_in_scope(&self, name: &str) -> Option<Type> {
+        if self.locals.in_function() {
+            return self.locals.find_tag_in_scope(name);
+        }
+        return self.globals.find_tag(name);
+    }
+
+    fn push_tag(&mut self, name: &str, ty: Type) {
+        if self.locals.in_function() {
+            self.locals.push_tag(name, ty);
+        } else {
+            self.globals.push_tag(name, ty);
+        }
+    }
+
+    fn push_typedef(&mut self, name: &str, ty: Type, span: Span) -> ParseResult<()> {
+        if self.locals.in_function() {
+            return self.locals.push_typedef(name, ty, span);
+        }
+        return self.globals.push_typedef(name, ty, span);
+    }
+
+    /// 識別子が typedef 名ならその型を返す
+    fn find_typedef(&self, name: &str) -> Option<Type> {
+        match self.find_symbol(name) {
+            Some(Symbol::Typedef(ty)) => return Some(ty),
+            _ => return None,
+        }
+    }
+
+    /// 現在のトークンが型名の始まりかどうか
+    ///
+    /// NOTE: 識別子は typedef 名として宣言されていれば型名とみなす
+    fn is_typename(&self) -> bool {
+        return self.is_typename_token(self.cursor.current());
+    }
+
+    fn is_typename_token(&self, token: &Token) -> bool {
+        match token.kind {
+            TokenKind::Keyword(keyword) if INTEGER_SPECIFIERS.contains(&keyword) => return true,
+            TokenKind::Keyword(Keyword::Void)
+            | TokenKind::Keyword(Keyword::Struct)
+            | TokenKind::Keyword(Keyword::Union)
+            | TokenKind::Keyword(Keyword::Enum) => return true,
+            TokenKind::Ident(name) => return self.find_typedef(name).is_some(),
+            _ => return false,
+        }
+    }
+
+    fn read_base_type(&mut self) -> ParseResult<Type> {
+        let mut ty = if self.cursor.consume_keyword(Keyword::Void) {
+            Type::Void
+        } else if self.cursor.consume_keyword(Keyword::Struct) {
+            self.struct_decl(false)?
+        } else if self.cursor.consume_keyword(Keyword::Union) {
+            self.struct_decl(true)?
+        } else if self.cursor.consume_keyword(Keyword::Enum) {
+            self.enum_decl()?
+        } else if let Some(ty) = self.typedef_name() {
+            ty
+        } else {
+            self.integer_type()?
+        };
+
+        while self.cursor.consume_sign("*") {
+            ty = Type::Pointer(Box::new(ty));
+        }
+        return Ok(ty);
+    }
+
+    /// 整数型の型指定子の並びを読む
+    ///
+    /// NOTE: "unsigned long int" と "long unsigned" のように順序は問わない
+    fn integer_type(&mut self) -> ParseResult<Type> {
+        let span = self.cursor.current().span;
+        let mut specifiers: Vec<Keyword> = Vec::new();
+        loop {
+            let keyword = match self.cursor.current().kind {
+                TokenKind::Keyword(keyword) if INTEGER_SPECIFIERS.contains(&keyword) => keyword,
+                _ => break,
+            };
+            self.cursor.consume_keyword(keyword);
+            specifiers.push(keyword);
+        }
+        if specifiers.is_empty() {
+            return Err(self.cursor.error("型名ではありません"));
         }
-        let ty = self.cursor.read_base_type();
-        let name = self.cursor.expect_ident();
-        let ty = self.cursor.read_type_suffix(ty);
-        let var = self.locals.new_var(name, ty);
-        params.push(var);
 
-        while !self.cursor.consume_sign(")") {
-            self.cursor.expect_sign(",");
-            let ty = self.cursor.read_base_type();
-            let name = self.cursor.expect_ident();
-            let ty = self.cursor.read_type_suffix(ty);
-            let var = self.locals.new_var(name, ty);
-            params.push(var);
+        let count = |keyword| specifiers.iter().filter(|k| **k == keyword).count();
+        let signed = count(Keyword::Signed);
+        let unsigned = count(Keyword::Unsigned);
+        let error = || {
+            return Err(Diagnostic::error("型指定子の組み合わせが不正です").with_span(span));
+        };
+        if signed + unsigned > 1 {
+            return error();
+        }
+        let is_unsigned = unsigned == 1;
+        let counts = (
+            count(Keyword::Bool),
+            count(Keyword::Char),
+            count(Keyword::Short),
+            count(Keyword::Int),
+            count(Keyword::Long),
+        );
+        match counts {
+            (1, 0, 0, 0, 0) if signed + unsigned == 0 => return Ok(Type::Bool),
+            (0, 1, 0, 0, 0) if is_unsigned => return Ok(Type::UChar),
+            (0, 1, 0, 0, 0) => return Ok(Type::Char),
+            (0, 0, 1, 0..=1, 0) if is_unsigned => return Ok(Type::UShort),
+            (0, 0, 1, 0..=1, 0) => return Ok(Type::Short),
+            (0, 0, 0, 0..=1, 0) if is_unsigned => return Ok(Type::UInt),
+            (0, 0, 0, 0..=1, 0) => return Ok(Type::Int),
+            (0, 0, 0, 0..=1, 1..=2) if is_unsigned => return Ok(Type::ULong),
+            (0, 0, 0, 0..=1, 1..=2) => return Ok(Type::Long),
+            _ => return error(),
         }
+    }
 
-        return params;
+    /// typedef 名を読む
+    fn typedef_name(&mut self) -> Option<Type> {
+        let ty = match self.cursor.current().kind {
+            TokenKind::Ident(name) => self.find_typedef(name)?,
+            _ => return None,
+        };
+        self.cursor.consume_ident();
+        return Some(ty);
+    }
+
+    /// "typedef" に続く宣言を読み、型名を登録する
+    fn typedef_decl(&mut self) -> ParseResult<()> {
+        let ty = self.read_base_type()?;
+        let name = self.cursor.expect_ident()?;
+        let span = self.cursor.prev_span();
+        let ty = self.read_type_suffix(ty)?;
+        self.cursor.expect_sign(";")?;
+        return self.push_typedef(name, ty, span);
     }
 
-    fn func_args(&mut self) -> Vec<Box<Node<'outer>>> {
+    fn read_type_suffix(&mut self, ty: Type) -> ParseResult<Type> {
+        let span = self.cursor.current().span;
+        let lengths = self.array_lengths()?;
+        return array_of(ty, &lengths, span);
+    }
+
+    /// "[" const-expr "]" の並びを読み、配列の要素数を外側から順に返す
+    fn array_lengths(&mut self) -> ParseResult<Vec<u32>> {
+        let mut lengths = Vec::new();
+        while self.cursor.consume_sign("[") {
+            let span = self.cursor.current().span;
+            let size = self.const_expr()?;
+            if size < 0 {
+                return Err(Diagnostic::error("配列の要素数が負です").with_span(span));
+            }
+            let size = u32::try_from(size)
+                .map_err(|_| Diagnostic::error("配列の要素数が大きすぎます").with_span(span))?;
+            self.cursor.expect_sign("]")?;
+            lengths.push(size);
+        }
+        return Ok(lengths);
+    }
+
+    /// sizeof や型変換に使う、変数名のない型を読む
+    fn type_name(&mut self) -> ParseResult<Type> {
+        let mut ty = self.read_base_type()?;
+        let span = self.cursor.current().span;
+        for (pointers, lengths) in self.abstract_declarator()? {
+            for _ in 0..pointers {
+                ty = Type::Pointer(Box::new(ty));
+            }
+            ty = array_of(ty, &lengths, span)?;
+        }
+        return Ok(ty);
+    }
+
+    /// 抽象宣言子を読み、外側から順にポインタの数と配列の要素数の組を返す
+    ///
+    /// NOTE: "int (*)[3]" のように括弧の中の宣言子は、括弧の外側を適用した型に対して適用する
+    fn abstract_declarator(&mut self) -> ParseResult<Vec<(u32, Vec<u32>)>> {
+        let mut pointers = 0;
+        while self.cursor.consume_sign("*") {
+            pointers += 1;
+        }
+        let mut inner = Vec::new();
+        if self.cursor.consume_sign("(") {
+            inner = self.abstract_declarator()?;
+            self.cursor.expect_sign(")")?;
+        }
+        let mut layers = vec![(pointers, self.array_lengths()?)];
+        layers.append(&mut inner);
+        return Ok(layers);
+    }
+
+    /// "struct" や "union" に続く、タグ名とメンバの宣言を読む
+    fn struct_decl(&mut self, is_union: bool) -> ParseResult<Type> {
+        let tag = self.cursor.consume_ident();
+        let span = self.cursor.prev_span();
+        let kind_error = |tag: &str| tag_kind_error(tag, span);
+
+        let ty = match tag {
+            // NOTE: `struct S;` は外側のスコープの S を隠して、新しい不完全型を宣言する
+            Some(tag) if !self.cursor.is_sign("{") => {
+                let found = if self.cursor.is_sign(";") {
+                    self.find_tag_in_scope(tag)
+                } else {
+                    self.find_tag(tag)
+                };
+                match found {
+                    Some(Type::Struct(st)) if st.is_union == is_union => {
+                        return Ok(Type::Struct(st))
+                    }
+                    Some(_) => return Err(kind_error(tag)),
+                    None => {
+                        let ty = Type::Struct(Rc::new(StructType::new(Some(tag), is_union)));
+                        self.push_tag(tag, ty.clone());
+                        return Ok(ty);
+                    }
+                }
+            }
+            // NOTE: メンバから自身を参照できるよう、メンバを読む前にタグを宣言しておく
+            Some(tag) => match self.find_tag_in_scope(tag) {
+                Some(Type::Struct(st)) if st.is_union == is_union => {
+                    if st.body.borrow().is_some() {
+                        return Err(
+                            Diagnostic::error(format!("\"{}\" が再定義されています", tag))
+                                .with_span(span),
+                        );
+                    }
+                    Type::Struct(st)
+                }
+                Some(_) => return Err(kind_error(tag)),
+                None => {
+                    let ty = Type::Struct(Rc::new(StructType::new(Some(tag), is_union)));
+                    self.push_tag(tag, ty.clone());
+                    ty
+                }
+            },
+            None => Type::Struct(Rc::new(StructType::new(None, is_union))),
+        };
+
+        self.cursor.expect_sign("{")?;
+        let body = self.struct_members(is_union)?;
+        if let Type::Struct(st) = &ty {
+            *st.body.borrow_mut() = Some(body);
+        }
+        return Ok(ty);
+    }
+
+    /// "enum" に続く、タグ名と列挙定数の宣言を読む
+    fn enum_decl(&mut self) -> ParseResult<Type> {
+        let tag = self.cursor.consume_ident();
+        let span = self.cursor.prev_span();
+
+        if let Some(tag) = tag {
+            if !self.cursor.is_sign("{") {
+                match self.find_tag(tag) {
+                    Some(Type::Enum) => return Ok(Type::Enum),
+                    Some(_) => return Err(tag_kind_error(tag, span)),
+                    None => {
+                        return Err(Diagnostic::error(format!(
+                            "未定義の列挙型 \"{}\" を参照しました。",
+                            tag
+                        ))
+                        .with_span(span))
+                    }
+                }
+            }
+            match self.find_tag_in_scope(tag) {
+                Some(Type::Enum) => {
+                    return Err(
+                        Diagnostic::error(format!("\"{}\" が再定義されています", tag))
+                            .with_span(span),
+                    )
+                }
+                Some(_) => return Err(tag_kind_error(tag, span)),
+                None => self.push_tag(tag, Type::Enum),
+            }
+        }
+
+        self.cursor.expect_sign("{")?;
+        let mut value = 0;
+        while !self.cursor.consume_sign("}") {
+            let name = self.cursor.expect_ident()?;
+            let span = self.cursor.prev_span();
+            if self.cursor.consume_sign("=") {
+                value = self.const_expr()?;
+            }
+            if value < i64::from(i32::MIN) || value > i64::from(i32::MAX) {
+                return Err(Diagnostic::error(format!(
+                    "列挙定数 \"{}\" の値 {} は int 型で表せません",
+                    name, value
+                ))
+                .with_span(span));
+            }
+            self.push_enum_const(name, value, span)?;
+            value = value.wrapping_add(1);
+            if !self.cursor.is_sign("}") {
+                self.cursor.expect_sign(",")?;
+            }
+        }
+        return Ok(Type::Enum);
+    }
+
+    fn struct_members(&mut self, is_union: bool) -> ParseResult<StructBody> {
+        let span = self.cursor.prev_span();
+        let mut members: Vec<(Option<String>, Type)> = Vec::new();
+        while !self.cursor.consume_sign("}") {
+            let base = self.read_base_type()?;
+            if self.cursor.consume_sign(";") {
+                // 無名の構造体・共用体はメンバとして展開する
+                if let Type::Struct(st) = &base {
+                    if st.tag.is_none() {
+                        members.push((None, base));
+                    }
+                }
+                continue;
+            }
+            let name = self.cursor.expect_ident()?;
+            let span = self.cursor.prev_span();
+            let ty = self.read_type_suffix(base)?;
+            self.cursor.expect_sign(";")?;
+            if ty.is_incomplete() {
+                return Err(
+                    Diagnostic::error(format!("メンバ \"{}\" の型が不完全です", name))
+                        .with_span(span),
+                );
+            }
+            if members.iter().any(|(n, _)| n.as_deref() == Some(name)) {
+                return Err(
+                    Diagnostic::error(format!("メンバ \"{}\" が重複しています", name))
+                        .with_span(span),
+                );
+            }
+            members.push((Some(String::from(name)), ty));
+        }
+        let kind = if is_union { "共用体" } else { "構造体" };
+        return StructBody::new(members, is_union)
+            .ok_or_else(|| Diagnostic::error(format!("{}が大きすぎます", kind)).with_span(span));
+    }
+
+    /// 引数の並びを読む
+    ///
+    /// "()" なら引数を検査しないため None を返す
+    fn read_func_params(&mut self) -> ParseResult<Option<Vec<Param<'outer>>>> {
+        let mut params = Vec::new();
+        if self.cursor.consume_sign(")") {
+            return Ok(None);
+        }
+        // NOTE: "(void)" は引数がないことを表す
+        if self.cursor.current().kind == TokenKind::Keyword(Keyword::Void)
+            && self.cursor.next().kind == TokenKind::Sign(")")
+        {
+            self.cursor.consume_keyword(Keyword::Void);
+            self.cursor.expect_sign(")")?;
+            return Ok(Some(params));
+        }
+        loop {
+            let ty = self.read_base_type()?;
+            let name = self.cursor.consume_ident();
+            let span = if name.is_some() {
+                self.cursor.prev_span()
+            } else {
+                self.cursor.current().span
+            };
+            // NOTE: 配列型の引数はポインタ型として扱う
+            let ty = decayed(&self.read_type_suffix(ty)?);
+            match ty {
+                Type::Void => {
+                    return Err(Diagnostic::error("void 型の引数は宣言できません").with_span(span))
+                }
+                Type::Struct(_) => {
+                    return Err(
+                        Diagnostic::error("構造体の値渡しには対応していません").with_span(span)
+                    )
+                }
+                _ => {}
+            }
+            if params.len() == MAX_ARGS {
+                return Err(too_many_args_error(span));
+            }
+            params.push((name, ty, span));
+            if self.cursor.consume_sign(")") {
+                return Ok(Some(params));
+            }
+            self.cursor.expect_sign(",")?;
+        }
+    }
+
+    fn func_args(&mut self) -> ParseResult<Vec<Node<'outer>>> {
         let mut args = Vec::new();
         if self.cursor.consume_sign(")") {
-            return args;
+            return Ok(args);
         }
-        args.push(Box::new(self.assign()));
+        args.push(self.assign()?);
         while self.cursor.consume_sign(",") {
-            args.push(Box::new(self.assign()));
+            args.push(self.assign()?);
         }
-        self.cursor.expect_sign(")");
-        return args;
+        self.cursor.expect_sign(")")?;
+        return Ok(args);
     }
 
-    fn declaretion(&mut self) -> Node<'outer> {
-        let ty = self.cursor.read_base_type();
-        let name = self.cursor.expect_ident();
-        let ty = self.cursor.read_type_suffix(ty);
-        let var = self.locals.new_var(name, ty);
+    /// 関数呼び出しの引数を読み、宣言された引数の型に変換する
+    ///
+    /// NOTE: 宣言されていない関数は int を返すものとして扱い、引数は整数拡張だけを行う
+    fn funcall(&mut self, name: &'outer str, span: Span) -> ParseResult<Node<'outer>> {
+        let mut args: Vec<Box<Node>> = self.func_args()?.into_iter().map(Box::new).collect();
+        if let Some(arg) = args.get(MAX_ARGS) {
+            return Err(too_many_args_error(arg.span));
+        }
+        let ty = match self.find_symbol(name) {
+            Some(Symbol::Function(ty)) => ty,
+            None => FunctionType {
+                return_ty: Type::Int,
+                params: None,
+            },
+            Some(_) => {
+                return Err(
+                    Diagnostic::error(format!("\"{}\" は関数ではありません", name)).with_span(span),
+                )
+            }
+        };
+
+        match &ty.params {
+            Some(params) => {
+                if params.len() != args.len() {
+                    return Err(Diagnostic::error(format!(
+                        "関数 \"{}\" の引数は {} 個ですが {} 個渡されています",
+                        name,
+                        params.len(),
+                        args.len()
+                    ))
+                    .with_span(span));
+                }
+                for (i, (arg, param)) in args.iter_mut().zip(params).enumerate() {
+                    if !is_assignable(param, arg) {
+                        return Err(Diagnostic::error(format!(
+                            "関数 \"{}\" の {} 番目の引数の型が異なります",
+                            name,
+                            i + 1
+                        ))
+                        .with_span(arg.span));
+                    }
+                    convert(arg, param);
+                }
+            }
+            None => {
+                if let Some(arg) = args
+                    .iter()
+                    .find(|arg| matches!(arg.ty, Some(Type::Struct(_))))
+                {
+                    return Err(
+                        Diagnostic::error("構造体の値渡しには対応していません").with_span(arg.span)
+                    );
+                }
+                args.iter_mut().for_each(promote);
+            }
+        }
+
+        let args = args.into_iter().map(|arg| *arg).collect();
+        let node = make_node(NodeKind::FunCall { name, args }, span)?;
+        return Ok(Node {
+            ty: Some(ty.return_ty),
+            ..node
+        });
+    }
+
+    fn declaretion(&mut self) -> ParseResult<Node<'outer>> {
+        let ty = self.read_base_type()?;
         if self.cursor.consume_sign(";") {
-            return make_node(NodeKind::Null);
+            return make_node(NodeKind::Null, self.cursor.prev_span());
+        }
+        let name = self.cursor.expect_ident()?;
+        let span = self.cursor.prev_span();
+        let ty = self.read_type_suffix(ty)?;
+        if ty.is_incomplete() {
+            return Err(
+                Diagnostic::error(format!("変数 \"{}\" の型が不完全です", name)).with_span(span),
+            );
         }
-        self.cursor.expect_sign("=");
-        let lhs = Box::new(make_node(NodeKind::Variable(var)));
-        let rhs = Box::new(self.expr());
-        self.cursor.expect_sign(";");
-        return make_node(NodeKind::Assign { lhs, rhs });
+        let var = self.locals.new_var(name, ty, span)?;
+        if self.cursor.consume_sign(";") {
+            return make_node(NodeKind::Null, span);
+        }
+        self.cursor.expect_sign("=")?;
+        let lhs = Box::new(make_node(NodeKind::Variable(var), span)?);
+        let rhs = Box::new(self.assign()?);
+        self.cursor.expect_sign(";")?;
+        return make_node(NodeKind::Assign { lhs, rhs }, span);
     }
 
-    fn primary(&mut self) -> Node<'outer> {
+    fn primary(&mut self) -> ParseResult<Node<'outer>> {
         if self.cursor.consume_keyword(Keyword::SizeOf) {
-            let target = Box::new(self.unary());
-            let size = target.ty.unwrap().size();
-            return make_node(NodeKind::Number(size));
+            let span = self.cursor.prev_span();
+            let ty = if self.cursor.is_sign("(") && self.is_typename_token(self.cursor.next()) {
+                self.cursor.expect_sign("(")?;
+                let ty = self.type_name()?;
+                self.cursor.expect_sign(")")?;
+                Some(ty)
+            } else {
+                self.unary()?.ty
+            };
+            let size = match ty {
+                Some(ty) if ty.is_incomplete() => {
+                    return Err(
+                        Diagnostic::error("不完全な型の sizeof は計算できません").with_span(span)
+                    )
+                }
+                Some(ty) => i64::from(ty.size()),
+                None => {
+                    return Err(Diagnostic::error("sizeof の対象の型が不明です").with_span(span))
+                }
+            };
+            return make_node(NodeKind::Number(size), span);
+        }
+        if self.cursor.consume_keyword(Keyword::AlignOf) {
+            let span = self.cursor.prev_span();
+            self.cursor.expect_sign("(")?;
+            let ty = self.type_name()?;
+            self.cursor.expect_sign(")")?;
+            if ty.is_incomplete() {
+                return Err(
+                    Diagnostic::error("不完全な型の _Alignof は計算できません").with_span(span)
+                );
+            }
+            return make_node(NodeKind::Number(i64::from(ty.align())), span);
         }
         if self.cursor.consume_sign("(") {
-            let node = self.expr();
-            self.cursor.expect_sign(")");
-            return node;
+            let node = self.expr()?;
+            self.cursor.expect_sign(")")?;
+            return Ok(node);
         }
         if let Some(name) = self.cursor.consume_ident() {
+            let span = self.cursor.prev_span();
             // funcall
             if self.cursor.consume_sign("(") {
-                let args = self.func_args();
-                return make_node(NodeKind::FunCall { name, args });
+                return self.funcall(name, span);
             }
 
-            // known variable
-            if let Some(local) = self.find_var(name) {
-                return make_node(NodeKind::Variable(local));
+            // known variable or enumeration constant
+            match self.find_symbol(name) {
+                Some(Symbol::Variable(var)) => return make_node(NodeKind::Variable(var), span),
+                Some(Symbol::EnumConst(value)) => return make_node(NodeKind::Number(value), span),
+                Some(Symbol::Typedef(_)) => {
+                    return Err(Diagnostic::error(format!(
+                        "型名 \"{}\" を式の中で使うことはできません",
+                        name
+                    ))
+                    .with_span(span))
+                }
+                Some(Symbol::Function(_)) => {
+                    return Err(Diagnostic::error(format!(
+                        "関数 \"{}\" を値として使うことはできません",
+                        name
+                    ))
+                    .with_span(span))
+                }
+                None => {}
             }
 
-            self.cursor
+            return Err(self
+                .cursor
                 .previous()
-                .report_error(&format!("未定義の変数 \"{}\" を参照しました。", name));
+                .error(&format!("未定義の変数 \"{}\" を参照しました。", name)));
         }
         // String literal
         if let Some(s) = self.cursor.consume_str() {
             let var = self.globals.string_literal(s);
-            return make_node(NodeKind::Variable(var));
+            return make_node(NodeKind::Variable(var), self.cursor.prev_span());
+        }
+        // Character constant (char is signed)
+        if let Some(c) = self.cursor.consume_char() {
+            return make_node(
+                NodeKind::Number(i64::from(c as i8)),
+                self.cursor.prev_span(),
+            );
         }
-        return make_node(NodeKind::Number(self.cursor.expect_number()));
+        let literal = self.cursor.expect_number()?;
+        let span = self.cursor.prev_span();
+        let ty = int_literal_type(&literal).ok_or_else(|| {
+            Diagnostic::error("整数リテラルが大きすぎて表せる型がありません").with_span(span)
+        })?;
+        return Ok(Node {
+            kind: NodeKind::Number(literal.value as i64),
+            ty: Some(ty),
+            span,
+        });
     }
 
-    fn postfix(&mut self) -> Node<'outer> {
-        let mut node = self.primary();
+    /// lhs に op を適用して代入するノードを作る
+    fn assign_op(
+        &self,
+        op: BinaryOp,
+        lhs: Node<'outer>,
+        rhs: Node<'outer>,
+        span: Span,
+    ) -> ParseResult<Node<'outer>> {
+        let op_ty = binary_type(op, &lhs, &rhs, span)?;
+        return make_node(
+            NodeKind::AssignOp {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                op_ty,
+            },
+            span,
+        );
+    }
 
-        while self.cursor.consume_sign("[") {
-            let exp = make_node(NodeKind::Add {
-                lhs: Box::new(node),
-                rhs: Box::new(self.expr()),
-            });
-            self.cursor.expect_sign("]");
-            node = make_node(NodeKind::Deref(Box::new(exp)));
+    /// "." や "->" に続くメンバ名を読む
+    fn struct_member(&mut self, target: Node<'outer>) -> ParseResult<Node<'outer>> {
+        let name = self.cursor.expect_ident()?;
+        let span = self.cursor.prev_span();
+        let error = |msg: String| Err(Diagnostic::error(msg).with_span(span));
+        let member = match &target.ty {
+            Some(Type::Struct(st)) if st.body.borrow().is_none() => {
+                return error(String::from("不完全な型のメンバは参照できません"))
+            }
+            Some(Type::Struct(st)) => match st.find_member(name) {
+                Some(member) => member,
+                None => return error(format!("メンバ \"{}\" が存在しません", name)),
+            },
+            _ => return error(String::from("構造体・共用体ではありません")),
+        };
+        return make_node(
+            NodeKind::Member {
+                target: Box::new(target),
+                member,
+            },
+            span,
+        );
+    }
+
+    fn postfix(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.primary()?;
+
+        loop {
+            if self.cursor.consume_sign("[") {
+                let span = self.cursor.prev_span();
+                let exp = make_node(
+                    NodeKind::Add {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.expr()?),
+                    },
+                    span,
+                )?;
+                self.cursor.expect_sign("]")?;
+                node = make_node(NodeKind::Deref(Box::new(exp)), span)?;
+            } else if self.cursor.consume_sign(".") {
+                node = self.struct_member(node)?;
+            } else if self.cursor.consume_sign("->") {
+                let span = self.cursor.prev_span();
+                node = make_node(NodeKind::Deref(Box::new(node)), span)?;
+                node = self.struct_member(node)?;
+            } else if self.cursor.consume_sign("++") {
+                let span = self.cursor.prev_span();
+                node = make_node(NodeKind::PostInc(Box::new(node)), span)?;
+            } else if self.cursor.consume_sign("--") {
+                let span = self.cursor.prev_span();
+                node = make_node(NodeKind::PostDec(Box::new(node)), span)?;
+            } else {
+                return Ok(node);
+            }
         }
-        return node;
     }
 
-    fn unary(&mut self) -> Node<'outer> {
+    fn unary(&mut self) -> ParseResult<Node<'outer>> {
+        if self.cursor.consume_sign("++") {
+            let span = self.cursor.prev_span();
+            let target = self.unary()?;
+            return self.assign_op(
+                BinaryOp::Add,
+                target,
+                make_node(NodeKind::Number(1), span)?,
+                span,
+            );
+        }
+        if self.cursor.consume_sign("--") {
+            let span = self.cursor.prev_span();
+            let target = self.unary()?;
+            return self.assign_op(
+                BinaryOp::Sub,
+                target,
+                make_node(NodeKind::Number(1), span)?,
+                span,
+            );
+        }
         if self.cursor.consume_sign("+") {
-            return self.primary();
+            let mut node = Box::new(self.cast()?);
+            promote(&mut node);
+            return Ok(*node);
         }
         if self.cursor.consume_sign("-") {
-            return make_node(NodeKind::Sub {
-                lhs: Box::new(make_node(NodeKind::Number(0))),
-                rhs: Box::new(self.primary()),
-            });
+            let span = self.cursor.prev_span();
+            return make_node(
+                NodeKind::Sub {
+                    lhs: Box::new(make_node(NodeKind::Number(0), span)?),
+                    rhs: Box::new(self.cast()?),
+                },
+                span,
+            );
+        }
+        if self.cursor.consume_sign("!") {
+            let span = self.cursor.prev_span();
+            return make_node(NodeKind::Not(Box::new(self.cast()?)), span);
+        }
+        if self.cursor.consume_sign("~") {
+            let span = self.cursor.prev_span();
+            return make_node(NodeKind::BitNot(Box::new(self.cast()?)), span);
         }
         if self.cursor.consume_sign("&") {
-            return make_node(NodeKind::Addr(Box::new(self.unary())));
+            let span = self.cursor.prev_span();
+            return make_node(NodeKind::Addr(Box::new(self.cast()?)), span);
         }
         if self.cursor.consume_sign("*") {
-            return make_node(NodeKind::Deref(Box::new(self.unary())));
+            let span = self.cursor.prev_span();
+            return make_node(NodeKind::Deref(Box::new(self.cast()?)), span);
         }
         return self.postfix();
     }
 
-    fn mul(&mut self) -> Node<'outer> {
-        let mut node = self.unary();
+    /// "(" type-name ")" に続く式を型変換する
+    fn cast(&mut self) -> ParseResult<Node<'outer>> {
+        if !(self.cursor.is_sign("(") && self.is_typename_token(self.cursor.next())) {
+            return self.unary();
+        }
+        self.cursor.expect_sign("(")?;
+        let span = self.cursor.prev_span();
+        let ty = self.type_name()?;
+        self.cursor.expect_sign(")")?;
+        if let Type::Array(..) | Type::Struct(_) = ty {
+            return Err(Diagnostic::error("配列や構造体には型変換できません").with_span(span));
+        }
+        let target = self.cast()?;
+        match (&target.ty, &ty) {
+            (Some(Type::Struct(_)), _) => {
+                return Err(Diagnostic::error("構造体は型変換できません").with_span(span))
+            }
+            (Some(Type::Void), Type::Void) => {}
+            (Some(Type::Void), _) => return Err(void_value_error(target.span)),
+            _ => {}
+        }
+        return Ok(Node {
+            kind: NodeKind::Cast(Box::new(target)),
+            ty: Some(ty),
+            span,
+        });
+    }
+
+    fn mul(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.cast()?;
 
         loop {
             if self.cursor.consume_sign("*") {
-                node = make_node(NodeKind::Mul {
-                    lhs: Box::new(node),
-                    rhs: Box::new(self.unary()),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::Mul {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.cast()?),
+                    },
+                    span,
+                )?;
             } else if self.cursor.consume_sign("/") {
-                node = make_node(NodeKind::Div {
-                    lhs: Box::new(node),
-                    rhs: Box::new(self.unary()),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::Div {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.cast()?),
+                    },
+                    span,
+                )?;
+            } else if self.cursor.consume_sign("%") {
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::Mod {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.cast()?),
+                    },
+                    span,
+                )?;
             } else {
-                return node;
+                return Ok(node);
             }
         }
     }
 
-    fn add(&mut self) -> Node<'outer> {
-        let mut node = self.mul();
+    fn add(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.mul()?;
 
         loop {
             if self.cursor.consume_sign("+") {
-                node = make_node(NodeKind::Add {
-                    lhs: Box::new(node),
-                    rhs: Box::new(self.mul()),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::Add {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.mul()?),
+                    },
+                    span,
+                )?;
             } else if self.cursor.consume_sign("-") {
-                node = make_node(NodeKind::Sub {
-                    lhs: Box::new(node),
-                    rhs: Box::new(self.mul()),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::Sub {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.mul()?),
+                    },
+                    span,
+                )?;
             } else {
-                return node;
+                return Ok(node);
             }
         }
     }
 
-    fn relational(&mut self) -> Node<'outer> {
-        let mut node = self.add();
+    fn shift(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.add()?;
+
+        loop {
+            if self.cursor.consume_sign("<<") {
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::Shl {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.add()?),
+                    },
+                    span,
+                )?;
+            } else if self.cursor.consume_sign(">>") {
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::Shr {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.add()?),
+                    },
+                    span,
+                )?;
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn relational(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.shift()?;
 
         loop {
             if self.cursor.consume_sign("<") {
-                node = make_node(NodeKind::LessThan {
-                    lhs: Box::new(node),
-                    rhs: Box::new(self.add()),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::LessThan {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.shift()?),
+                    },
+                    span,
+                )?;
             } else if self.cursor.consume_sign("<=") {
-                node = make_node(NodeKind::LessThanEqual {
-                    lhs: Box::new(node),
-                    rhs: Box::new(self.add()),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::LessThanEqual {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.shift()?),
+                    },
+                    span,
+                )?;
             } else if self.cursor.consume_sign(">") {
-                node = make_node(NodeKind::LessThan {
-                    lhs: Box::new(self.add()),
-                    rhs: Box::new(node),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::LessThan {
+                        lhs: Box::new(self.shift()?),
+                        rhs: Box::new(node),
+                    },
+                    span,
+                )?;
             } else if self.cursor.consume_sign(">=") {
-                node = make_node(NodeKind::LessThanEqual {
-                    lhs: Box::new(self.add()),
-                    rhs: Box::new(node),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::LessThanEqual {
+                        lhs: Box::new(self.shift()?),
+                        rhs: Box::new(node),
+                    },
+                    span,
+                )?;
             } else {
-                return node;
+                return Ok(node);
             }
         }
     }
 
-    fn equality(&mut self) -> Node<'outer> {
-        let mut node = self.relational();
+    fn equality(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.relational()?;
 
         loop {
             if self.cursor.consume_sign("==") {
-                node = make_node(NodeKind::Equal {
-                    lhs: Box::new(node),
-                    rhs: Box::new(self.relational()),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::Equal {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.relational()?),
+                    },
+                    span,
+                )?;
             } else if self.cursor.consume_sign("!=") {
-                node = make_node(NodeKind::NotEqual {
-                    lhs: Box::new(node),
-                    rhs: Box::new(self.relational()),
-                });
+                let span = self.cursor.prev_span();
+                node = make_node(
+                    NodeKind::NotEqual {
+                        lhs: Box::new(node),
+                        rhs: Box::new(self.relational()?),
+                    },
+                    span,
+                )?;
             } else {
-                return node;
+                return Ok(node);
             }
         }
     }
 
-    fn assign(&mut self) -> Node<'outer> {
-        let mut node = self.equality();
+    fn bitand(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.equality()?;
+
+        while self.cursor.consume_sign("&") {
+            let span = self.cursor.prev_span();
+            node = make_node(
+                NodeKind::BitAnd {
+                    lhs: Box::new(node),
+                    rhs: Box::new(self.equality()?),
+                },
+                span,
+            )?;
+        }
+        return Ok(node);
+    }
+
+    fn bitxor(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.bitand()?;
+
+        while self.cursor.consume_sign("^") {
+            let span = self.cursor.prev_span();
+            node = make_node(
+                NodeKind::BitXor {
+                    lhs: Box::new(node),
+                    rhs: Box::new(self.bitand()?),
+                },
+                span,
+            )?;
+        }
+        return Ok(node);
+    }
+
+    fn bitor(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.bitxor()?;
+
+        while self.cursor.consume_sign("|") {
+            let span = self.cursor.prev_span();
+            node = make_node(
+                NodeKind::BitOr {
+                    lhs: Box::new(node),
+                    rhs: Box::new(self.bitxor()?),
+                },
+                span,
+            )?;
+        }
+        return Ok(node);
+    }
+
+    fn logand(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.bitor()?;
+
+        while self.cursor.consume_sign("&&") {
+            let span = self.cursor.prev_span();
+            node = make_node(
+                NodeKind::LogAnd {
+                    lhs: Box::new(node),
+                    rhs: Box::new(self.bitor()?),
+                },
+                span,
+            )?;
+        }
+        return Ok(node);
+    }
+
+    fn logor(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.logand()?;
+
+        while self.cursor.consume_sign("||") {
+            let span = self.cursor.prev_span();
+            node = make_node(
+                NodeKind::LogOr {
+                    lhs: Box::new(node),
+                    rhs: Box::new(self.logand()?),
+                },
+                span,
+            )?;
+        }
+        return Ok(node);
+    }
+
+    /// 整数定数式を読む
+    fn const_expr(&mut self) -> ParseResult<i64> {
+        let span = self.cursor.current().span;
+        let node = self.conditional()?;
+        return eval_const(&node)
+            .ok_or_else(|| Diagnostic::error("整数定数式ではありません").with_span(span));
+    }
+
+    fn conditional(&mut self) -> ParseResult<Node<'outer>> {
+        let node = self.logor()?;
+        if !self.cursor.consume_sign("?") {
+            return Ok(node);
+        }
+        let span = self.cursor.prev_span();
+        let then = self.expr()?;
+        self.cursor.expect_sign(":")?;
+        let els = self.conditional()?;
+        return make_node(
+            NodeKind::Cond {
+                cond: Box::new(node),
+                then: Box::new(then),
+                els: Box::new(els),
+            },
+            span,
+        );
+    }
+
+    fn assign(&mut self) -> ParseResult<Node<'outer>> {
+        let node = self.conditional()?;
         if self.cursor.consume_sign("=") {
-            node = make_node(NodeKind::Assign {
-                lhs: Box::new(node),
-                rhs: Box::new(self.assign()),
-            });
+            let span = self.cursor.prev_span();
+            return make_node(
+                NodeKind::Assign {
+                    lhs: Box::new(node),
+                    rhs: Box::new(self.assign()?),
+                },
+                span,
+            );
         }
-        return node;
+        for (sign, op) in ASSIGN_OPS {
+            if self.cursor.consume_sign(sign) {
+                let span = self.cursor.prev_span();
+                let rhs = self.assign()?;
+                return self.assign_op(*op, node, rhs, span);
+            }
+        }
+        return Ok(node);
     }
 
-    fn expr(&mut self) -> Node<'outer> {
-        return self.assign();
+    fn expr(&mut self) -> ParseResult<Node<'outer>> {
+        let mut node = self.assign()?;
+
+        while self.cursor.consume_sign(",") {
+            let span = self.cursor.prev_span();
+            node = make_node(
+                NodeKind::Comma {
+                    lhs: Box::new(node),
+                    rhs: Box::new(self.assign()?),
+                },
+                span,
+            )?;
+        }
+        return Ok(node);
     }
 
-    fn stmt(&mut self) -> Node<'outer> {
+    /// ループの本体を読む
+    fn loop_body(&mut self) -> ParseResult<Node<'outer>> {
+        self.break_depth += 1;
+        self.continue_depth += 1;
+        let node = self.stmt();
+        self.break_depth -= 1;
+        self.continue_depth -= 1;
+        return node;
+    }
+
+    fn stmt(&mut self) -> ParseResult<Node<'outer>> {
         // block
         if self.cursor.consume_sign("{") {
-            let mut nodes: Vec<Box<Node<'outer>>> = Vec::new();
+            let span = self.cursor.prev_span();
+            let mut nodes: Vec<Node<'outer>> = Vec::new();
+            self.locals.enter_scope();
             while !self.cursor.consume_sign("}") {
-                nodes.push(Box::new(self.stmt()));
+                nodes.push(self.stmt()?);
             }
-            return make_node(NodeKind::Block(nodes));
+            self.locals.leave_scope();
+            return make_node(NodeKind::Block(nodes), span);
         }
 
         // if
         if self.cursor.consume_keyword(Keyword::If) {
-            self.cursor.expect_sign("(");
-            let cond = Box::new(self.expr());
-            self.cursor.expect_sign(")");
-            let then = Box::new(self.stmt());
+            let span = self.cursor.prev_span();
+            self.cursor.expect_sign("(")?;
+            let cond = Box::new(self.expr()?);
+            self.cursor.expect_sign(")")?;
+            let then = Box::new(self.stmt()?);
             let els = if self.cursor.consume_keyword(Keyword::Else) {
-                Some(Box::new(self.stmt()))
+                Some(Box::new(self.stmt()?))
             } else {
                 None
             };
-            return make_node(NodeKind::If { cond, then, els });
+            return make_node(NodeKind::If { cond, then, els }, span);
         }
 
         // while
         if self.cursor.consume_keyword(Keyword::While) {
-            self.cursor.expect_sign("(");
-            let cond = Box::new(self.expr());
-            self.cursor.expect_sign(")");
-            let then = Box::new(self.stmt());
-            return make_node(NodeKind::While { cond, then });
+            let span = self.cursor.prev_span();
+            self.cursor.expect_sign("(")?;
+            let cond = Box::new(self.expr()?);
+            self.cursor.expect_sign(")")?;
+            let then = Box::new(self.loop_body()?);
+            return make_node(NodeKind::While { cond, then }, span);
         }
 
         // for
         if self.cursor.consume_keyword(Keyword::For) {
-            self.cursor.expect_sign("(");
-            let init = if !self.cursor.consume_sign(";") {
-                let node = self.expr();
-                self.cursor.expect_sign(";");
-                Some(Box::new(node))
-            } else {
+            let span = self.cursor.prev_span();
+            self.cursor.expect_sign("(")?;
+            // NOTE: 初期化節で宣言した変数は for 文の中だけで有効
+            self.locals.enter_scope();
+            let init = if self.cursor.consume_sign(";") {
                 None
+            } else if self.is_typename() {
+                Some(Box::new(self.declaretion()?))
+            } else {
+                let node = self.expr()?;
+                self.cursor.expect_sign(";")?;
+                Some(Box::new(node))
             };
             let cond = if !self.cursor.consume_sign(";") {
-                let node = self.expr();
-                self.cursor.expect_sign(";");
+                let node = self.expr()?;
+                self.cursor.expect_sign(";")?;
                 Some(Box::new(node))
             } else {
                 None
             };
             let inc = if !self.cursor.consume_sign(")") {
-                let node = self.expr();
-                self.cursor.expect_sign(")");
+                let node = self.expr()?;
+                self.cursor.expect_sign(")")?;
                 Some(Box::new(node))
             } else {
                 None
             };
-            let then = Box::new(self.stmt());
-            return make_node(NodeKind::For {
-                init,
-                cond,
-                inc,
-                then,
+            let then = Box::new(self.loop_body()?);
+            self.locals.leave_scope();
+            return make_node(
+                NodeKind::For {
+                    init,
+                    cond,
+                    inc,
+                    then,
+                },
+                span,
+            );
+        }
+
+        // do-while
+        if self.cursor.consume_keyword(Keyword::Do) {
+            let span = self.cursor.prev_span();
+            let then = Box::new(self.loop_body()?);
+            self.cursor.expect_keyword(Keyword::While)?;
+            self.cursor.expect_sign("(")?;
+            let cond = Box::new(self.expr()?);
+            self.cursor.expect_sign(")")?;
+            self.cursor.expect_sign(";")?;
+            return make_node(NodeKind::DoWhile { then, cond }, span);
+        }
+
+        // switch
+        if self.cursor.consume_keyword(Keyword::Switch) {
+            let span = self.cursor.prev_span();
+            self.cursor.expect_sign("(")?;
+            let cond = Box::new(self.expr()?);
+            self.cursor.expect_sign(")")?;
+            let ty = match &cond.ty {
+                Some(ty) if ty.is_integer() => integer_promoted(ty),
+                Some(Type::Void) => return Err(void_value_error(cond.span)),
+                _ => {
+                    return Err(
+                        Diagnostic::error("switch 文の条件式は整数でなければなりません")
+                            .with_span(cond.span),
+                    )
+                }
+            };
+            self.switches.push(SwitchContext {
+                ty,
+                cases: Vec::new(),
+                has_default: false,
             });
+            self.break_depth += 1;
+            let then = self.stmt();
+            self.break_depth -= 1;
+            let switch = self.switches.pop().unwrap();
+            return make_node(
+                NodeKind::Switch {
+                    cond,
+                    then: Box::new(then?),
+                    cases: switch.cases,
+                    has_default: switch.has_default,
+                },
+                span,
+            );
+        }
+
+        // case
+        if self.cursor.consume_keyword(Keyword::Case) {
+            let span = self.cursor.prev_span();
+            if self.switches.is_empty() {
+                return Err(
+                    Diagnostic::error("switch 文の外で case を使うことはできません")
+                        .with_span(span),
+                );
+            }
+            let value = self.const_expr()?;
+            self.cursor.expect_sign(":")?;
+            let switch = self.switches.last_mut().unwrap();
+            let value = truncated(value, &switch.ty);
+            if switch.cases.contains(&value) {
+                return Err(
+                    Diagnostic::error(format!("case の値 {} が重複しています", value))
+                        .with_span(span),
+                );
+            }
+            switch.cases.push(value);
+            let index = switch.cases.len() - 1;
+            let stmt = Box::new(self.stmt()?);
+            return make_node(NodeKind::Case { index, stmt }, span);
+        }
+
+        // default
+        if self.cursor.consume_keyword(Keyword::Default) {
+            let span = self.cursor.prev_span();
+            let switch = match self.switches.last_mut() {
+                Some(switch) => switch,
+                None => {
+                    return Err(
+                        Diagnostic::error("switch 文の外で default を使うことはできません")
+                            .with_span(span),
+                    )
+                }
+            };
+            if switch.has_default {
+                return Err(Diagnostic::error("default が重複しています").with_span(span));
+            }
+            switch.has_default = true;
+            self.cursor.expect_sign(":")?;
+            let stmt = Box::new(self.stmt()?);
+            return make_node(NodeKind::Default(stmt), span);
+        }
+
+        // break
+        if self.cursor.consume_keyword(Keyword::Break) {
+            let span = self.cursor.prev_span();
+            if self.break_depth == 0 {
+                return Err(Diagnostic::error(
+                    "ループや switch 文の外で break を使うことはできません",
+                )
+                .with_span(span));
+            }
+            self.cursor.expect_sign(";")?;
+            return make_node(NodeKind::Break, span);
+        }
+
+        // continue
+        if self.cursor.consume_keyword(Keyword::Continue) {
+            let span = self.cursor.prev_span();
+            if self.continue_depth == 0 {
+                return Err(
+                    Diagnostic::error("ループの外で continue を使うことはできません")
+                        .with_span(span),
+                );
+            }
+            self.cursor.expect_sign(";")?;
+            return make_node(NodeKind::Continue, span);
+        }
+
+        // goto
+        if self.cursor.consume_keyword(Keyword::Goto) {
+            let name = self.cursor.expect_ident()?;
+            let span = self.cursor.prev_span();
+            self.gotos.push((name, span));
+            self.cursor.expect_sign(";")?;
+            return make_node(NodeKind::Goto(name), span);
+        }
+
+        // label
+        let span = self.cursor.current().span;
+        if let Some(name) = self.cursor.consume_label() {
+            if self.labels.insert(name, span).is_some() {
+                return Err(Diagnostic::error(format!(
+                    "ラベル \"{}\" が重複して定義されています",
+                    name
+                ))
+                .with_span(span));
+            }
+            let stmt = Box::new(self.stmt()?);
+            return make_node(NodeKind::Label { name, stmt }, span);
         }
 
         // return
         if self.cursor.consume_keyword(Keyword::Return) {
-            let node = make_node(NodeKind::Return(Box::new(self.expr())));
-            self.cursor.expect_sign(";");
-            return node;
+            let span = self.cursor.prev_span();
+            let return_ty = self.return_ty.clone().unwrap();
+            if self.cursor.consume_sign(";") {
+                if return_ty != Type::Void {
+                    return Err(Diagnostic::error("return 文に戻り値がありません").with_span(span));
+                }
+                return make_node(NodeKind::Return(None), span);
+            }
+            let mut target = Box::new(self.expr()?);
+            if return_ty == Type::Void {
+                return Err(
+                    Diagnostic::error("void 型の関数から値を返すことはできません").with_span(span),
+                );
+            }
+            if target.ty == Some(Type::Void) {
+                return Err(void_value_error(target.span));
+            }
+            if !is_assignable(&return_ty, &target) {
+                return Err(
+                    Diagnostic::error("戻り値の型が関数の型と異なります").with_span(target.span)
+                );
+            }
+            convert(&mut target, &return_ty);
+            let node = make_node(NodeKind::Return(Some(target)), span)?;
+            self.cursor.expect_sign(";")?;
+            return Ok(node);
+        }
+
+        // typedef
+        if self.cursor.consume_keyword(Keyword::Typedef) {
+            let span = self.cursor.prev_span();
+            self.typedef_decl()?;
+            return make_node(NodeKind::Null, span);
         }
 
         // declaretion
-        if self.cursor.is_typename() {
+        if self.is_typename() {
             return self.declaretion();
         }
 
-        let node = self.expr();
-        self.cursor.expect_sign(";");
-        return node;
+        let node = self.expr()?;
+        self.cursor.expect_sign(";")?;
+        return Ok(node);
     }
 
+    /// トップレベルの宣言を一つ読む
+    ///
+    /// 関数定義ならその関数を返し、グローバル変数などの宣言なら None を返す
     pub fn parse(
-        ident: &'outer str,
         cursor: &'local mut TokenCursor<'outer>,
-        globals: &'local mut GlobalHolder<'outer>,
-    ) -> Option<Function<'outer>> {
-        if !cursor.consume_sign("(") {
-            return None;
-        }
+        globals: &'local mut GlobalHolder,
+    ) -> ParseResult<Option<Function<'outer>>> {
         let mut ctx = FunctionParser::new(cursor, globals);
+        if ctx.cursor.consume_keyword(Keyword::Typedef) {
+            ctx.typedef_decl()?;
+            return Ok(None);
+        }
+        let ty = ctx.read_base_type()?;
+        if ctx.cursor.consume_sign(";") {
+            return Ok(None);
+        }
+        let ident = ctx.cursor.expect_ident()?;
+        let span = ctx.cursor.prev_span();
+        // function
+        if ctx.cursor.consume_sign("(") {
+            return ctx.function(ident, ty, span);
+        }
+        // global-var
+        let ty = ctx.read_type_suffix(ty)?;
+        ctx.cursor.expect_sign(";")?;
+        if ty.is_incomplete() {
+            return Err(
+                Diagnostic::error(format!("変数 \"{}\" の型が不完全です", ident)).with_span(span),
+            );
+        }
+        ctx.globals.push(
+            Variable {
+                name: String::from(ident),
+                offset: 0,
+                ty,
+                is_local: false,
+                content: None,
+            },
+            span,
+        )?;
+        return Ok(None);
+    }
+
+    /// 関数の定義を読む (プロトタイプ宣言なら登録だけして None を返す)
+    fn function(
+        mut self,
+        ident: &'outer str,
+        return_ty: Type,
+        span: Span,
+    ) -> ParseResult<Option<Function<'outer>>> {
+        if let Type::Struct(_) = return_ty {
+            return Err(Diagnostic::error("構造体の値渡しには対応していません").with_span(span));
+        }
         let mut nodes = Vec::new();
+        self.return_ty = Some(return_ty.clone());
 
-        let params = ctx.read_func_params();
-        ctx.cursor.expect_sign("{");
-        while !ctx.cursor.consume_sign("}") {
-            nodes.push(ctx.stmt());
+        self.locals.enter_scope();
+        let params = self.read_func_params()?;
+        let ty = FunctionType {
+            return_ty: return_ty.clone(),
+            params: params
+                .as_ref()
+                .map(|params| params.iter().map(|(_, ty, _)| ty.clone()).collect()),
+        };
+        // NOTE: 再帰呼び出しできるよう、本体を読む前に登録する
+        let is_definition = !self.cursor.consume_sign(";");
+        self.globals.push_function(ident, ty, is_definition, span)?;
+        if !is_definition {
+            return Ok(None);
+        }
+        let mut vars = Vec::new();
+        for (name, ty, span) in params.unwrap_or_default() {
+            let name =
+                name.ok_or_else(|| Diagnostic::error("引数名がありません").with_span(span))?;
+            vars.push(self.locals.new_var(name, ty, span)?);
+        }
+        self.cursor.expect_sign("{")?;
+        while !self.cursor.consume_sign("}") {
+            nodes.push(self.stmt()?);
+        }
+        for (name, span) in &self.gotos {
+            if !self.labels.contains_key(name) {
+                return Err(Diagnostic::error(format!(
+                    "未定義のラベル \"{}\" を参照しました。",
+                    name
+                ))
+                .with_span(*span));
+            }
         }
 
-        let stack_size = ctx.locals.stack_size();
-        return Some(Function {
+        let stack_size = self.locals.stack_size();
+        return Ok(Some(Function {
             name: ident,
-            params,
-            locals: ctx.locals.dump_to_vec(),
+            return_ty,
+            params: vars,
+            locals: self.locals.dump_to_vec(),
             nodes,
             stack_size,
-        });
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(value: u64, unsigned: bool, long: bool, decimal: bool) -> IntLiteral {
+        return IntLiteral {
+            value,
+            unsigned,
+            long,
+            decimal,
+        };
+    }
+
+    #[test]
+    fn test_int_literal_type() {
+        let max_int = Type::Int.max_value();
+        let ty = |l: IntLiteral| int_literal_type(&l);
+        assert_eq!(ty(literal(42, false, false, true)), Some(Type::Int));
+        assert_eq!(ty(literal(42, true, false, true)), Some(Type::UInt));
+        assert_eq!(ty(literal(42, false, true, true)), Some(Type::Long));
+        assert_eq!(ty(literal(42, true, true, false)), Some(Type::ULong));
+        assert_eq!(
+            ty(literal(max_int + 1, false, false, false)),
+            Some(Type::UInt)
+        );
+        assert_eq!(
+            ty(literal(max_int + 1, false, false, true)),
+            Some(Type::Long)
+        );
+        let max_long = Type::Long.max_value();
+        assert_eq!(
+            ty(literal(max_long + 1, false, true, false)),
+            Some(Type::ULong)
+        );
+        let max_uint = Type::UInt.max_value();
+        assert_eq!(ty(literal(max_uint, true, false, true)), Some(Type::UInt));
+        assert_eq!(ty(literal(u64::MAX, true, true, true)), Some(Type::ULong));
+        assert_eq!(ty(literal(u64::MAX, false, false, true)), None);
+    }
+
+    #[test]
+    fn test_truncated() {
+        assert_eq!(truncated(300, &Type::Char), 44);
+        assert_eq!(truncated(255, &Type::Char), -1);
+        assert_eq!(truncated(-1, &Type::UChar), 255);
+        assert_eq!(truncated(-1, &Type::UShort), 65535);
+        assert_eq!(truncated(2147483648, &Type::Int), -2147483648);
+        assert_eq!(truncated(-1, &Type::UInt), 4294967295);
+        assert_eq!(truncated(-1, &Type::ULong), -1);
+        assert_eq!(truncated(256, &Type::Bool), 1);
     }
 }
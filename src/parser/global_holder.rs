@@ -1,16 +1,30 @@
+use super::token_cursor::ParseResult;
 use super::types::*;
+use crate::diagnostic::{Diagnostic, Span};
 use std::rc::Rc;
 use std::string::String;
 use std::vec::Vec;
 
-pub struct GlobalHolder<'a> {
-    vars: Vec<Rc<Variable<'a>>>,
+pub struct GlobalHolder {
+    vars: Vec<Rc<Variable>>,
+    // ファイルスコープで宣言された列挙定数
+    consts: Vec<(String, i64)>,
+    // ファイルスコープで宣言された typedef の型名
+    typedefs: Vec<(String, Type)>,
+    // ファイルスコープで宣言された構造体・共用体・列挙型のタグ
+    tags: Vec<(String, Type)>,
+    // 宣言された関数の型と、定義済みかどうか
+    functions: Vec<(String, FunctionType, bool)>,
     label_id: u32,
 }
-impl<'a> GlobalHolder<'a> {
-    pub fn new() -> GlobalHolder<'a> {
+impl GlobalHolder {
+    pub fn new() -> GlobalHolder {
         return GlobalHolder {
             vars: Vec::new(),
+            consts: Vec::new(),
+            typedefs: Vec::new(),
+            tags: Vec::new(),
+            functions: Vec::new(),
             label_id: 0,
         };
     }
@@ -20,34 +34,192 @@ impl<'a> GlobalHolder<'a> {
         return format!(".L.data.{}", self.label_id);
     }
 
-    pub fn find_var(&self, name: &str) -> Option<Rc<Variable<'a>>> {
-        return self
-            .vars
-            .iter()
-            .find(|&var| var.name == name)
-            .map(|var| var.clone());
+    /// グローバル変数や列挙定数、型名、関数を探す
+    pub fn find(&self, name: &str) -> Option<Symbol> {
+        if let Some((_, ty, _)) = self.functions.iter().find(|(n, ..)| n == name) {
+            return Some(Symbol::Function(ty.clone()));
+        }
+        if let Some((_, value)) = self.consts.iter().find(|(n, _)| n == name) {
+            return Some(Symbol::EnumConst(*value));
+        }
+        if let Some((_, ty)) = self.typedefs.iter().find(|(n, _)| n == name) {
+            return Some(Symbol::Typedef(ty.clone()));
+        }
+        let var = self.vars.iter().find(|&var| var.name == name)?;
+        return Some(Symbol::Variable(var.clone()));
+    }
+
+    pub fn push_const(&mut self, name: &str, value: i64, span: Span) -> ParseResult<()> {
+        if self.find(name).is_some() {
+            return Err(Diagnostic::error(format!(
+                "列挙定数 \"{}\" が同じスコープで再定義されています",
+                name
+            ))
+            .with_span(span));
+        }
+        self.consts.push((String::from(name), value));
+        return Ok(());
     }
 
-    pub fn push(&mut self, var: Variable<'a>) {
+    pub fn push(&mut self, var: Variable, span: Span) -> ParseResult<()> {
+        match self.find(&var.name) {
+            Some(Symbol::Variable(_)) | None => {}
+            Some(_) => {
+                return Err(Diagnostic::error(format!(
+                    "\"{}\" が同じスコープで再定義されています",
+                    var.name
+                ))
+                .with_span(span))
+            }
+        }
         self.vars.push(Rc::new(var));
+        return Ok(());
+    }
+
+    pub fn push_typedef(&mut self, name: &str, ty: Type, span: Span) -> ParseResult<()> {
+        if self.find(name).is_some() {
+            return Err(Diagnostic::error(format!(
+                "型名 \"{}\" が同じスコープで再定義されています",
+                name
+            ))
+            .with_span(span));
+        }
+        self.typedefs.push((String::from(name), ty));
+        return Ok(());
+    }
+
+    /// 関数の宣言や定義を登録する
+    ///
+    /// NOTE: 以前の宣言と引数の型が食い違っていても、片方が "()" なら引数の型が分かる方を残す
+    pub fn push_function(
+        &mut self,
+        name: &str,
+        ty: FunctionType,
+        is_definition: bool,
+        span: Span,
+    ) -> ParseResult<()> {
+        let error = |msg: String| Err(Diagnostic::error(msg).with_span(span));
+        let index = match self.functions.iter().position(|(n, ..)| n == name) {
+            Some(index) => index,
+            None if self.find(name).is_some() => {
+                return error(format!("\"{}\" が同じスコープで再定義されています", name))
+            }
+            None => {
+                self.functions.push((String::from(name), ty, is_definition));
+                return Ok(());
+            }
+        };
+        let (_, prev_ty, defined) = &mut self.functions[index];
+        if is_definition && *defined {
+            return error(format!("関数 \"{}\" が再定義されています", name));
+        }
+        let compatible = match (&prev_ty.params, &ty.params) {
+            (Some(prev), Some(params)) => prev == params,
+            _ => true,
+        };
+        if prev_ty.return_ty != ty.return_ty || !compatible {
+            return error(format!("関数 \"{}\" の型が以前の宣言と異なります", name));
+        }
+        if ty.params.is_some() {
+            prev_ty.params = ty.params;
+        }
+        *defined |= is_definition;
+        return Ok(());
+    }
+
+    pub fn push_tag(&mut self, name: &str, ty: Type) {
+        self.tags.push((String::from(name), ty));
+    }
+
+    pub fn find_tag(&self, name: &str) -> Option<Type> {
+        return self
+            .tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, ty)| ty.clone());
     }
 
-    pub fn string_literal(&mut self, s: &'a str) -> Rc<Variable<'a>> {
-        let mut array_size = s.len() as u32;
-        // NOTE: For string termination: '\0'
-        array_size += 1;
+    /// bytes は終端の '\0' を含むこと
+    pub fn string_literal(&mut self, bytes: Vec<u8>) -> Rc<Variable> {
+        let array_size = bytes.len() as u32;
         let var = Rc::new(Variable {
             ty: Type::Array(Box::new(Type::Char), array_size),
             name: self.gen_label_id(),
             offset: 0,
             is_local: false,
-            content: Some(s),
+            content: Some(bytes),
         });
         self.vars.push(var.clone());
-        return var.clone();
+        return var;
     }
 
-    pub fn dump_to_vec(self) -> Vec<Rc<Variable<'a>>> {
+    pub fn dump_to_vec(self) -> Vec<Rc<Variable>> {
         return self.vars;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_function() {
+        let span = Span::dummy();
+        let ty = |params: Option<Vec<Type>>| FunctionType {
+            return_ty: Type::Int,
+            params,
+        };
+        let mut globals = GlobalHolder::new();
+        globals.push_function("f", ty(None), false, span).unwrap();
+        globals
+            .push_function("f", ty(Some(vec![Type::Char])), true, span)
+            .unwrap();
+        assert_eq!(
+            globals.find("f"),
+            Some(Symbol::Function(ty(Some(vec![Type::Char]))))
+        );
+
+        // NOTE: 引数の型が分かっている宣言は "()" の宣言で上書きしない
+        globals.push_function("f", ty(None), false, span).unwrap();
+        assert_eq!(
+            globals.find("f"),
+            Some(Symbol::Function(ty(Some(vec![Type::Char]))))
+        );
+
+        assert!(globals
+            .push_function("f", ty(Some(vec![Type::Int])), false, span)
+            .is_err());
+        assert!(globals.push_function("f", ty(None), true, span).is_err());
+    }
+
+    #[test]
+    fn test_push_var() {
+        let span = Span::dummy();
+        let var = |name: &str| Variable {
+            name: String::from(name),
+            offset: 0,
+            ty: Type::Int,
+            is_local: false,
+            content: None,
+        };
+        let mut globals = GlobalHolder::new();
+        globals
+            .push_function(
+                "f",
+                FunctionType {
+                    return_ty: Type::Int,
+                    params: None,
+                },
+                false,
+                span,
+            )
+            .unwrap();
+        globals.push_const("A", 1, span).unwrap();
+        globals.push_typedef("t", Type::Int, span).unwrap();
+        assert!(globals.push(var("f"), span).is_err());
+        assert!(globals.push(var("A"), span).is_err());
+        assert!(globals.push(var("t"), span).is_err());
+        globals.push(var("x"), span).unwrap();
+        assert!(matches!(globals.find("x"), Some(Symbol::Variable(_))));
+    }
+}
@@ -0,0 +1,203 @@
+use super::token_cursor::ParseResult;
+use super::types::*;
+use crate::diagnostic::{Diagnostic, Span};
+use std::rc::Rc;
+use std::string::String;
+use std::vec::Vec;
+
+/// ブロックスコープ
+struct Scope {
+    vars: Vec<Rc<Variable>>,
+    // 列挙定数
+    consts: Vec<(String, i64)>,
+    // typedef で宣言された型名
+    typedefs: Vec<(String, Type)>,
+    // 構造体・共用体・列挙型のタグ
+    tags: Vec<(String, Type)>,
+    // スコープに入った時点のスタックの使用量
+    offset: u32,
+}
+
+/// ローカル変数と、関数内で宣言された列挙定数や型名、タグを管理する
+///
+/// NOTE: スコープを抜けるとその変数の領域は解放され、後続の兄弟スコープで再利用される
+pub struct LocalHolder {
+    locals: Vec<Rc<Variable>>,
+    scopes: Vec<Scope>,
+    offset: u32,
+    max_offset: u32,
+}
+impl LocalHolder {
+    pub fn new() -> LocalHolder {
+        return LocalHolder {
+            locals: Vec::new(),
+            scopes: Vec::new(),
+            offset: 0,
+            max_offset: 0,
+        };
+    }
+
+    pub fn stack_size(&self) -> u32 {
+        return align_to(self.max_offset, 8);
+    }
+
+    /// 関数の中にいるかどうか (スコープがなければファイルスコープ)
+    pub fn in_function(&self) -> bool {
+        return !self.scopes.is_empty();
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(Scope {
+            vars: Vec::new(),
+            consts: Vec::new(),
+            typedefs: Vec::new(),
+            tags: Vec::new(),
+            offset: self.offset,
+        });
+    }
+
+    pub fn leave_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        self.offset = scope.offset;
+    }
+
+    pub fn new_var(&mut self, name: &str, ty: Type, span: Span) -> ParseResult<Rc<Variable>> {
+        if self.is_declared_in_scope(name) {
+            return Err(Diagnostic::error(format!(
+                "変数 \"{}\" が同じスコープで再定義されています",
+                name
+            ))
+            .with_span(span));
+        }
+        self.offset = self
+            .offset
+            .checked_add(ty.size())
+            .and_then(|offset| checked_align_to(offset, ty.align()))
+            .ok_or_else(|| Diagnostic::error("ローカル変数の領域が大きすぎます").with_span(span))?;
+        let scope = self.scopes.last_mut().unwrap();
+        self.max_offset = self.max_offset.max(self.offset);
+        let var = Rc::new(Variable {
+            name: String::from(name),
+            offset: self.offset,
+            ty,
+            is_local: true,
+            content: None,
+        });
+        scope.vars.push(var.clone());
+        self.locals.push(var.clone());
+        return Ok(var);
+    }
+
+    pub fn push_const(&mut self, name: &str, value: i64, span: Span) -> ParseResult<()> {
+        if self.is_declared_in_scope(name) {
+            return Err(Diagnostic::error(format!(
+                "列挙定数 \"{}\" が同じスコープで再定義されています",
+                name
+            ))
+            .with_span(span));
+        }
+        let scope = self.scopes.last_mut().unwrap();
+        scope.consts.push((String::from(name), value));
+        return Ok(());
+    }
+
+    pub fn push_typedef(&mut self, name: &str, ty: Type, span: Span) -> ParseResult<()> {
+        if self.is_declared_in_scope(name) {
+            return Err(Diagnostic::error(format!(
+                "型名 \"{}\" が同じスコープで再定義されています",
+                name
+            ))
+            .with_span(span));
+        }
+        let scope = self.scopes.last_mut().unwrap();
+        scope.typedefs.push((String::from(name), ty));
+        return Ok(());
+    }
+
+    fn is_declared_in_scope(&self, name: &str) -> bool {
+        let scope = self.scopes.last().unwrap();
+        return scope.vars.iter().any(|var| var.name == name)
+            || scope.consts.iter().any(|(n, _)| n == name)
+            || scope.typedefs.iter().any(|(n, _)| n == name);
+    }
+
+    /// 内側のスコープから順に変数や列挙定数、型名を探す
+    pub fn find(&self, name: &str) -> Option<Symbol> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(var) = scope.vars.iter().find(|var| var.name == name) {
+                return Some(Symbol::Variable(var.clone()));
+            }
+            if let Some((_, value)) = scope.consts.iter().find(|(n, _)| n == name) {
+                return Some(Symbol::EnumConst(*value));
+            }
+            if let Some((_, ty)) = scope.typedefs.iter().find(|(n, _)| n == name) {
+                return Some(Symbol::Typedef(ty.clone()));
+            }
+        }
+        return None;
+    }
+
+    pub fn push_tag(&mut self, name: &str, ty: Type) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.tags.push((String::from(name), ty));
+    }
+
+    /// 内側のスコープから順にタグを探す
+    pub fn find_tag(&self, name: &str) -> Option<Type> {
+        for scope in self.scopes.iter().rev() {
+            if let Some((_, ty)) = scope.tags.iter().find(|(tag, _)| tag == name) {
+                return Some(ty.clone());
+            }
+        }
+        return None;
+    }
+
+    /// 最も内側のスコープだけからタグを探す
+    pub fn find_tag_in_scope(&self, name: &str) -> Option<Type> {
+        let scope = self.scopes.last()?;
+        return scope
+            .tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, ty)| ty.clone());
+    }
+
+    pub fn dump_to_vec(self) -> Vec<Rc<Variable>> {
+        return self.locals;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_scope() {
+        let span = Span::dummy();
+        let mut locals = LocalHolder::new();
+        locals.enter_scope();
+        let x = locals.new_var("x", Type::Int, span).unwrap();
+
+        locals.enter_scope();
+        let inner_x = locals.new_var("x", Type::Int, span).unwrap();
+        assert_eq!(locals.find("x"), Some(Symbol::Variable(inner_x.clone())));
+        assert!(locals.new_var("x", Type::Int, span).is_err());
+        locals.leave_scope();
+        assert_eq!(locals.find("x"), Some(Symbol::Variable(x)));
+
+        // NOTE: 列挙定数も変数と同じ名前空間に属する
+        locals.enter_scope();
+        locals.push_const("x", 3, span).unwrap();
+        assert_eq!(locals.find("x"), Some(Symbol::EnumConst(3)));
+        assert!(locals.new_var("x", Type::Int, span).is_err());
+        locals.leave_scope();
+
+        // NOTE: 兄弟のスコープでは同じ領域を再利用する
+        locals.enter_scope();
+        let y = locals.new_var("y", Type::Int, span).unwrap();
+        assert_eq!(y.offset, inner_x.offset);
+        locals.leave_scope();
+        assert_eq!(locals.find("y"), None);
+        assert_eq!(locals.stack_size(), 8);
+    }
+}
@@ -1,5 +1,6 @@
 mod function_parser;
 mod global_holder;
+mod local_holder;
 mod parse;
 mod token_cursor;
 mod types;
@@ -1,15 +1,15 @@
-use super::types::*;
-use crate::tokenizer::{Keyword, Token, TokenKind};
-use std::cmp::max;
-use std::vec::Vec;
+use crate::diagnostic::{Diagnostic, Span};
+use crate::tokenizer::{IntLiteral, Keyword, Token, TokenKind};
+
+pub type ParseResult<T> = Result<T, Diagnostic>;
 
 pub struct TokenCursor<'a> {
-    tokens: &'a Vec<Token<'a>>,
+    tokens: &'a [Token<'a>],
     index: usize,
 }
 
 impl<'a> TokenCursor<'a> {
-    pub fn new(tokens: &'a Vec<Token<'a>>) -> TokenCursor<'a> {
+    pub fn new(tokens: &'a [Token<'a>]) -> TokenCursor<'a> {
         return TokenCursor { tokens, index: 0 };
     }
 
@@ -18,7 +18,17 @@ impl<'a> TokenCursor<'a> {
     }
 
     pub fn previous(&self) -> &Token<'a> {
-        return &self.tokens[max(self.index - 1, 0)];
+        return &self.tokens[self.index.saturating_sub(1)];
+    }
+
+    /// 現在の次のトークン (末尾では Eof を返す)
+    pub fn next(&self) -> &Token<'a> {
+        return &self.tokens[(self.index + 1).min(self.tokens.len() - 1)];
+    }
+
+    /// 直前に読んだトークンの位置
+    pub fn prev_span(&self) -> Span {
+        return self.previous().span;
     }
 
     fn seek(&mut self) {
@@ -32,8 +42,13 @@ impl<'a> TokenCursor<'a> {
         };
     }
 
-    pub fn report_error(&self, msg: &str) -> ! {
-        self.current().report_error(msg);
+    pub fn error(&self, msg: &str) -> Diagnostic {
+        return self.current().error(msg);
+    }
+
+    /// 読み進めずに、現在のトークンが sign かどうかを調べる
+    pub fn is_sign(&self, sign: &str) -> bool {
+        return self.current().kind == TokenKind::Sign(sign);
     }
 
     pub fn consume_sign(&mut self, sign: &str) -> bool {
@@ -66,9 +81,23 @@ impl<'a> TokenCursor<'a> {
         }
     }
 
-    pub fn consume_str(&mut self) -> Option<&'a str> {
-        match self.current().kind {
+    /// `ident ":"` の形のラベルを読む
+    pub fn consume_label(&mut self) -> Option<&'a str> {
+        match (&self.current().kind, self.tokens.get(self.index + 1)) {
+            (TokenKind::Ident(name), Some(next)) if next.kind == TokenKind::Sign(":") => {
+                let name = *name;
+                self.seek();
+                self.seek();
+                return Some(name);
+            }
+            _ => return None,
+        }
+    }
+
+    pub fn consume_str(&mut self) -> Option<Vec<u8>> {
+        match &self.current().kind {
             TokenKind::Str(s) => {
+                let s = s.clone();
                 self.seek();
                 return Some(s);
             }
@@ -76,71 +105,47 @@ impl<'a> TokenCursor<'a> {
         }
     }
 
-    pub fn expect_keyword(&mut self, keyword: Keyword) {
+    pub fn consume_char(&mut self) -> Option<u8> {
+        match self.current().kind {
+            TokenKind::Char(c) => {
+                self.seek();
+                return Some(c);
+            }
+            _ => return None,
+        }
+    }
+
+    pub fn expect_keyword(&mut self, keyword: Keyword) -> ParseResult<()> {
         if self.consume_keyword(keyword) {
-            return;
+            return Ok(());
         }
-        self.report_error(&format!("'{}' ではありません", keyword));
+        return Err(self.error(&format!("'{}' ではありません", keyword)));
     }
 
-    pub fn expect_sign(&mut self, op: &str) {
+    pub fn expect_sign(&mut self, op: &str) -> ParseResult<()> {
         if self.consume_sign(op) {
-            return;
+            return Ok(());
         }
-        self.report_error(&format!("'{}' ではありません", op));
+        return Err(self.error(&format!("'{}' ではありません", op)));
     }
 
-    pub fn expect_number(&mut self) -> u32 {
+    pub fn expect_number(&mut self) -> ParseResult<IntLiteral> {
         match self.current().kind {
             TokenKind::Number(n) => {
                 self.seek();
-                return n;
+                return Ok(n);
             }
-            _ => self.report_error("数ではありません"),
+            _ => return Err(self.error("数ではありません")),
         }
     }
 
-    pub fn expect_ident(&mut self) -> &'a str {
+    pub fn expect_ident(&mut self) -> ParseResult<&'a str> {
         match self.current().kind {
             TokenKind::Ident(ident) => {
                 self.seek();
-                return ident;
+                return Ok(ident);
             }
-            _ => self.report_error("数ではありません"),
+            _ => return Err(self.error("識別子ではありません")),
         }
     }
-
-    pub fn read_base_type(&mut self) -> Type {
-        let mut ty = if self.consume_keyword(Keyword::Char) {
-            Type::Char
-        } else {
-            self.expect_keyword(Keyword::Int);
-            Type::Int
-        };
-
-        loop {
-            if !self.consume_sign("*") {
-                break;
-            }
-            ty = Type::Pointer(Box::new(ty));
-        }
-        return ty;
-    }
-
-    pub fn read_type_suffix(&mut self, ty: Type) -> Type {
-        if !self.consume_sign("[") {
-            return ty;
-        }
-        let size = self.expect_number();
-        self.expect_sign("]");
-        let ty = self.read_type_suffix(ty);
-        return Type::Array(Box::new(ty), size);
-    }
-
-    pub fn is_typename(&mut self) -> bool {
-        return match self.current().kind {
-            TokenKind::Keyword(Keyword::Char) | TokenKind::Keyword(Keyword::Int) => true,
-            _ => false,
-        };
-    }
 }
@@ -1,32 +1,248 @@
+use crate::diagnostic::Span;
+use std::cell::RefCell;
+use std::fmt;
 use std::rc::Rc;
 use std::string::String;
 
+pub fn align_to(n: u32, align: u32) -> u32 {
+    return (n + align - 1) & !(align - 1);
+}
+
+/// 桁あふれする場合は None を返す align_to
+pub fn checked_align_to(n: u32, align: u32) -> Option<u32> {
+    return Some(n.checked_add(align - 1)? & !(align - 1));
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Type {
+    Void,
+    Bool,
     Char,
+    UChar,
+    Short,
+    UShort,
     Int,
+    UInt,
+    // NOTE: long long も long と同じ表現を使う
+    Long,
+    ULong,
     Pointer(Box<Type>),
     Array(Box<Type>, u32),
+    Struct(Rc<StructType>),
+    // NOTE: 列挙型は int と同じ表現を使う
+    Enum,
 }
 impl Type {
+    /// 要素数 length の配列型 (大きさが u32 に収まらなければ None)
+    pub fn array_of(el: Type, length: u32) -> Option<Type> {
+        el.size().checked_mul(length)?;
+        return Some(Type::Array(Box::new(el), length));
+    }
+
     pub fn size(&self) -> u32 {
         match self {
-            Type::Char => 1,
-            Type::Int | Type::Pointer(_) => 8,
+            // NOTE: gcc と同じく void* の加減算は 1 バイト単位で行う
+            Type::Void | Type::Bool | Type::Char | Type::UChar => 1,
+            Type::Short | Type::UShort => 2,
+            Type::Int | Type::UInt | Type::Enum => 4,
+            Type::Long | Type::ULong | Type::Pointer(_) => 8,
             Type::Array(el, array_size) => el.size() * array_size,
+            Type::Struct(st) => st.body.borrow().as_ref().map_or(0, |body| body.size),
+        }
+    }
+
+    pub fn align(&self) -> u32 {
+        match self {
+            Type::Array(el, _) => el.align(),
+            Type::Struct(st) => st.body.borrow().as_ref().map_or(1, |body| body.align),
+            _ => self.size(),
+        }
+    }
+
+    /// 大きさの決まっていない不完全型かどうか
+    pub fn is_incomplete(&self) -> bool {
+        match self {
+            Type::Array(el, _) => el.is_incomplete(),
+            Type::Struct(st) => st.body.borrow().is_none(),
+            Type::Void => true,
+            _ => false,
         }
     }
+
+    pub fn is_integer(&self) -> bool {
+        return matches!(
+            self,
+            Type::Bool
+                | Type::Char
+                | Type::UChar
+                | Type::Short
+                | Type::UShort
+                | Type::Int
+                | Type::UInt
+                | Type::Long
+                | Type::ULong
+                | Type::Enum
+        );
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        return matches!(
+            self,
+            Type::Bool | Type::UChar | Type::UShort | Type::UInt | Type::ULong
+        );
+    }
+
+    /// 整数型で表せる最大値
+    pub fn max_value(&self) -> u64 {
+        let bits = self.size() * 8;
+        if self.is_unsigned() {
+            return u64::MAX >> (64 - bits);
+        }
+        return u64::MAX >> (65 - bits);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Member {
+    // 無名の構造体・共用体のメンバは None
+    pub name: Option<String>,
+    pub ty: Type,
+    pub offset: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructBody {
+    pub members: Vec<Member>,
+    pub size: u32,
+    pub align: u32,
+}
+impl StructBody {
+    /// メンバの配置を決める (大きさが u32 に収まらなければ None)
+    pub fn new(members: Vec<(Option<String>, Type)>, is_union: bool) -> Option<StructBody> {
+        let mut offset: u32 = 0;
+        let mut size = 0;
+        let mut align = 1;
+        let mut laid_out = Vec::new();
+        for (name, ty) in members {
+            align = align.max(ty.align());
+            if is_union {
+                size = size.max(ty.size());
+                laid_out.push(Member {
+                    name,
+                    ty,
+                    offset: 0,
+                });
+                continue;
+            }
+            offset = checked_align_to(offset, ty.align())?;
+            let member = Member { name, ty, offset };
+            offset = offset.checked_add(member.ty.size())?;
+            size = offset;
+            laid_out.push(member);
+        }
+        return Some(StructBody {
+            members: laid_out,
+            size: checked_align_to(size, align)?,
+            align,
+        });
+    }
+}
+
+/// 構造体・共用体の型
+///
+/// NOTE: 自己参照する構造体を表せるよう参照で共有し、メンバは定義を読んだ時点で埋める
+pub struct StructType {
+    pub tag: Option<String>,
+    pub is_union: bool,
+    // 不完全型の間は None
+    pub body: RefCell<Option<StructBody>>,
+}
+impl StructType {
+    pub fn new(tag: Option<&str>, is_union: bool) -> StructType {
+        return StructType {
+            tag: tag.map(String::from),
+            is_union,
+            body: RefCell::new(None),
+        };
+    }
+
+    /// メンバを探す (無名のメンバの中も探す)
+    pub fn find_member(&self, name: &str) -> Option<Member> {
+        let body = self.body.borrow();
+        for member in &body.as_ref()?.members {
+            match (&member.name, &member.ty) {
+                (Some(n), _) if n == name => return Some(member.clone()),
+                (None, Type::Struct(st)) => {
+                    if let Some(mut found) = st.find_member(name) {
+                        found.offset += member.offset;
+                        return Some(found);
+                    }
+                }
+                _ => {}
+            }
+        }
+        return None;
+    }
+}
+
+/// 同じ宣言から作られた型だけを同じ型とみなす
+impl PartialEq for StructType {
+    fn eq(&self, other: &StructType) -> bool {
+        return std::ptr::eq(self, other);
+    }
+}
+
+impl fmt::Debug for StructType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.is_union { "union" } else { "struct" };
+        match &self.tag {
+            Some(tag) => return write!(f, "{} {}", kind, tag),
+            None => return write!(f, "{} <anonymous>", kind),
+        }
+    }
+}
+
+/// 関数の戻り値と引数の型
+#[derive(Debug, PartialEq, Clone)]
+pub struct FunctionType {
+    pub return_ty: Type,
+    // "()" で宣言された関数は引数を検査しないため None
+    pub params: Option<Vec<Type>>,
+}
+
+/// 変数や列挙定数、typedef 名など、通常の識別子が指すもの
+#[derive(Debug, PartialEq, Clone)]
+pub enum Symbol {
+    Variable(Rc<Variable>),
+    EnumConst(i64),
+    Typedef(Type),
+    Function(FunctionType),
 }
 
 #[derive(Debug, PartialEq)]
-pub struct Variable<'a> {
+pub struct Variable {
     pub name: String,
     pub ty: Type,
     pub is_local: bool,
     // local variable
     pub offset: u32,
     // global variable
-    pub content: Option<&'a str>,
+    pub content: Option<Vec<u8>>,
+}
+
+/// 複合代入で使う二項演算子
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Shl,
+    Shr,
+    BitAnd,
+    BitOr,
+    BitXor,
 }
 
 #[derive(Debug, PartialEq)]
@@ -34,7 +250,7 @@ pub enum NodeKind<'a> {
     // 何もしないノード
     Null,
     // 整数
-    Number(u32),
+    Number(i64),
     // "+"
     Add {
         lhs: Box<Node<'a>>,
@@ -55,6 +271,21 @@ pub enum NodeKind<'a> {
         lhs: Box<Node<'a>>,
         rhs: Box<Node<'a>>,
     },
+    // "%"
+    Mod {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
+    // "<<"
+    Shl {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
+    // ">>"
+    Shr {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
     // "=="
     Equal {
         lhs: Box<Node<'a>>,
@@ -75,15 +306,69 @@ pub enum NodeKind<'a> {
         lhs: Box<Node<'a>>,
         rhs: Box<Node<'a>>,
     },
+    // "&"
+    BitAnd {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
+    // "|"
+    BitOr {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
+    // "^"
+    BitXor {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
+    // "~"
+    BitNot(Box<Node<'a>>),
+    // 型変換 (ノードの型に変換する)
+    Cast(Box<Node<'a>>),
+    // "&&"
+    LogAnd {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
+    // "||"
+    LogOr {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
+    // "!"
+    Not(Box<Node<'a>>),
+    // "?:"
+    Cond {
+        cond: Box<Node<'a>>,
+        then: Box<Node<'a>>,
+        els: Box<Node<'a>>,
+    },
+    // ","
+    Comma {
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+    },
     // "="
     Assign {
         lhs: Box<Node<'a>>,
         rhs: Box<Node<'a>>,
     },
+    // "+=", "-=" など (前置の "++", "--" を含む)
+    AssignOp {
+        op: BinaryOp,
+        lhs: Box<Node<'a>>,
+        rhs: Box<Node<'a>>,
+        // 演算を行う型
+        op_ty: Option<Type>,
+    },
+    // 後置の "++"
+    PostInc(Box<Node<'a>>),
+    // 後置の "--"
+    PostDec(Box<Node<'a>>),
     // variable
-    Variable(Rc<Variable<'a>>),
-    // "return"
-    Return(Box<Node<'a>>),
+    Variable(Rc<Variable>),
+    // "return" (値を返さない場合は None)
+    Return(Option<Box<Node<'a>>>),
     // "if"
     If {
         cond: Box<Node<'a>>,
@@ -102,36 +387,139 @@ pub enum NodeKind<'a> {
         inc: Option<Box<Node<'a>>>,
         then: Box<Node<'a>>,
     },
+    // "do" ... "while"
+    DoWhile {
+        then: Box<Node<'a>>,
+        cond: Box<Node<'a>>,
+    },
+    // "switch"
+    Switch {
+        cond: Box<Node<'a>>,
+        then: Box<Node<'a>>,
+        // case ラベルの値で、添字が Case の index に対応する
+        cases: Vec<i64>,
+        has_default: bool,
+    },
+    // "case"
+    Case {
+        index: usize,
+        stmt: Box<Node<'a>>,
+    },
+    // "default"
+    Default(Box<Node<'a>>),
+    // "break"
+    Break,
+    // "continue"
+    Continue,
+    // "goto"
+    Goto(&'a str),
+    // ラベル付きの文
+    Label {
+        name: &'a str,
+        stmt: Box<Node<'a>>,
+    },
     // "{" "}"
-    Block(Vec<Box<Node<'a>>>),
+    Block(Vec<Node<'a>>),
     // "func()"
     FunCall {
         name: &'a str,
-        args: Vec<Box<Node<'a>>>,
+        args: Vec<Node<'a>>,
     },
     // "&val"
     Addr(Box<Node<'a>>),
     // "*ptr"
     Deref(Box<Node<'a>>),
+    // "val.member" ("ptr->member" は Deref を挟む)
+    Member {
+        target: Box<Node<'a>>,
+        member: Member,
+    },
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Node<'a> {
     pub kind: NodeKind<'a>,
     pub ty: Option<Type>,
+    pub span: Span,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Function<'a> {
     pub name: &'a str,
+    pub return_ty: Type,
     pub stack_size: u32,
-    pub params: Vec<Rc<Variable<'a>>>,
-    pub locals: Vec<Rc<Variable<'a>>>,
+    pub params: Vec<Rc<Variable>>,
+    pub locals: Vec<Rc<Variable>>,
     pub nodes: Vec<Node<'a>>,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Program<'a> {
     pub functions: Vec<Function<'a>>,
-    pub globals: Vec<Rc<Variable<'a>>>,
+    pub globals: Vec<Rc<Variable>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_struct_layout() {
+        let members = vec![
+            (Some(String::from("a")), Type::Char),
+            (Some(String::from("b")), Type::Int),
+            (
+                Some(String::from("c")),
+                Type::Array(Box::new(Type::Char), 3),
+            ),
+        ];
+        let body = StructBody::new(members.clone(), false).unwrap();
+        let offsets: Vec<u32> = body.members.iter().map(|m| m.offset).collect();
+        assert_eq!(offsets, vec![0, 4, 8]);
+        assert_eq!((body.size, body.align), (12, 4));
+
+        let body = StructBody::new(members, true).unwrap();
+        assert!(body.members.iter().all(|m| m.offset == 0));
+        assert_eq!((body.size, body.align), (4, 4));
+
+        // NOTE: 無名のメンバの中のメンバは外側のオフセットを足して返す
+        let inner = StructType::new(None, false);
+        *inner.body.borrow_mut() =
+            StructBody::new(vec![(Some(String::from("y")), Type::Int)], false);
+        let outer = StructType::new(Some("s"), false);
+        *outer.body.borrow_mut() = StructBody::new(
+            vec![
+                (Some(String::from("x")), Type::Char),
+                (None, Type::Struct(Rc::new(inner))),
+            ],
+            false,
+        );
+        assert_eq!(outer.find_member("y").map(|m| m.offset), Some(4));
+        assert_eq!(outer.find_member("z"), None);
+    }
+
+    #[test]
+    fn test_size_overflow() {
+        assert_eq!(Type::array_of(Type::Int, 0x7fffffff), None);
+        let row = Type::array_of(Type::Char, 1 << 30).unwrap();
+        assert_eq!(Type::array_of(row.clone(), 4), None);
+        assert_eq!(Type::array_of(row, 3).map(|ty| ty.size()), Some(3 << 30));
+
+        assert_eq!(Type::array_of(Type::Int, 1 << 30), None);
+        let big = Type::array_of(Type::Int, 1 << 29).unwrap();
+        let members = vec![
+            (Some(String::from("a")), big.clone()),
+            (Some(String::from("b")), big),
+        ];
+        assert!(StructBody::new(members.clone(), false).is_none());
+        assert!(StructBody::new(members, true).is_some());
+        let members = vec![
+            (Some(String::from("a")), Type::Char),
+            (
+                Some(String::from("b")),
+                Type::array_of(Type::Char, u32::MAX).unwrap(),
+            ),
+        ];
+        assert!(StructBody::new(members, false).is_none());
+    }
 }
@@ -0,0 +1,45 @@
+use std::string::String;
+use std::vec::Vec;
+
+pub type FileId = usize;
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub content: String,
+}
+
+impl SourceFile {
+    /// index を含む行の内容 (改行を含まない)
+    pub fn line_at(&self, index: usize) -> &str {
+        let start = self.content[..index].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.content[index..]
+            .find('\n')
+            .map_or(self.content.len(), |i| index + i);
+        return &self.content[start..end];
+    }
+}
+
+/// コンパイル対象のファイルを FileId で引けるように保持する
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        return SourceMap { files: Vec::new() };
+    }
+
+    pub fn add(&mut self, name: &str, content: String) -> FileId {
+        self.files.push(SourceFile {
+            name: String::from(name),
+            content,
+        });
+        return self.files.len() - 1;
+    }
+
+    pub fn get(&self, id: FileId) -> &SourceFile {
+        return &self.files[id];
+    }
+}
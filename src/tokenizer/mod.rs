@@ -1,11 +1,25 @@
 mod types;
 
+use crate::diagnostic::{Diagnostic, Span};
+use crate::source::FileId;
 use std::vec::Vec;
-pub use types::{Keyword, Token, TokenKind};
+pub use types::{IntLiteral, Keyword, Token, TokenKind};
 
-const SIGNES: &'static [&str] = &[
-    "==", "!=", "<=", ">=", "<", ">", "(", ")", "+", "-", "*", "/", "&", "=", ";", "{", "}", ",",
-    "[", "]",
+/// C11 の区切り子 (6.4.6)
+const SIGNES: &[&str] = &[
+    "...", "<<=", ">>=", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=",
+    "/=", "%=", "+=", "-=", "&=", "^=", "|=", "##", "[", "]", "(", ")", "{", "}", ".", "&", "*",
+    "+", "-", "~", "!", "/", "%", "<", ">", "^", "|", "?", ":", ";", "=", ",", "#",
+];
+
+/// 二重音字と、それが表す区切り子
+const DIGRAPHS: &[(&str, &str)] = &[
+    ("%:%:", "##"),
+    ("<:", "["),
+    (":>", "]"),
+    ("<%", "{"),
+    ("%>", "}"),
+    ("%:", "#"),
 ];
 
 fn is_alpha(c: &char) -> bool {
@@ -13,17 +27,133 @@ fn is_alpha(c: &char) -> bool {
 }
 
 fn is_alnum(c: &char) -> bool {
-    return is_alpha(c) || c.is_digit(10);
+    return is_alpha(c) || c.is_ascii_digit();
+}
+
+/// `\` に続くエスケープシーケンスを読み、(値, 読んだバイト数) を返す
+fn decode_escape(s: &[u8]) -> Result<(u8, usize), &'static str> {
+    match s.first() {
+        None => return Err("エスケープシーケンスが途切れています。"),
+        Some(b'0'..=b'7') => {
+            let len = s
+                .iter()
+                .take(3)
+                .take_while(|c| matches!(c, b'0'..=b'7'))
+                .count();
+            let value = s[..len]
+                .iter()
+                .fold(0u32, |acc, c| acc * 8 + u32::from(c - b'0'));
+            if value > 0xff {
+                return Err("エスケープシーケンスの値が大きすぎます。");
+            }
+            return Ok((value as u8, len));
+        }
+        Some(b'x') => {
+            let len = s[1..].iter().take_while(|c| c.is_ascii_hexdigit()).count();
+            if len == 0 {
+                return Err("\\x の後に16進数がありません。");
+            }
+            let mut value: u32 = 0;
+            for c in &s[1..=len] {
+                value = value * 16 + (*c as char).to_digit(16).unwrap();
+                if value > 0xff {
+                    return Err("エスケープシーケンスの値が大きすぎます。");
+                }
+            }
+            return Ok((value as u8, len + 1));
+        }
+        Some(c) => {
+            let value = match c {
+                b'a' => 0x07,
+                b'b' => 0x08,
+                b't' => b'\t',
+                b'n' => b'\n',
+                b'v' => 0x0b,
+                b'f' => 0x0c,
+                b'r' => b'\r',
+                // NOTE: GNU 拡張 (ESC)
+                b'e' => 0x1b,
+                _ => *c,
+            };
+            return Ok((value, 1));
+        }
+    }
+}
+
+/// 整数リテラルの接尾辞を読み、(unsigned, long) を返す
+fn parse_int_suffix(s: &str) -> Option<(bool, bool)> {
+    let (unsigned, rest) = match s.strip_prefix(|c| c == 'u' || c == 'U') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (long, rest) = match rest {
+        _ if rest.starts_with("ll") || rest.starts_with("LL") => (true, &rest[2..]),
+        _ if rest.starts_with('l') || rest.starts_with('L') => (true, &rest[1..]),
+        _ => (false, rest),
+    };
+    match rest {
+        "" => return Some((unsigned, long)),
+        "u" | "U" if !unsigned => return Some((true, long)),
+        _ => return None,
+    }
+}
+
+fn parse_int_literal(s: &str) -> Result<IntLiteral, &'static str> {
+    let lower = s.to_ascii_lowercase();
+    let (radix, body) = if lower.starts_with("0x") {
+        (16, &s[2..])
+    } else if lower.starts_with("0b") {
+        (2, &s[2..])
+    } else if s.starts_with('0') {
+        (8, s)
+    } else {
+        (10, s)
+    };
+
+    let digits_len = body
+        .find(|c: char| !c.is_ascii_hexdigit() || (radix != 16 && c.is_ascii_alphabetic()))
+        .unwrap_or(body.len());
+    let (digits, suffix) = body.split_at(digits_len);
+    if digits.is_empty() {
+        return Err("数字がありません。");
+    }
+    let (unsigned, long) = parse_int_suffix(suffix).ok_or("不正な整数リテラルの接尾辞です。")?;
+
+    let mut value: u64 = 0;
+    for c in digits.chars() {
+        let n = c
+            .to_digit(radix)
+            .ok_or("その基数では使えない数字が含まれています。")?;
+        value = value
+            .checked_mul(u64::from(radix))
+            .and_then(|v| v.checked_add(u64::from(n)))
+            .ok_or("整数リテラルが大きすぎます。")?;
+    }
+    return Ok(IntLiteral {
+        value,
+        unsigned,
+        long,
+        decimal: radix == 10,
+    });
 }
 
 struct TokenizerContext<'a> {
     input: &'a str,
+    file_id: FileId,
     index: usize,
+    line: usize,
+    col: usize,
 }
 
 impl<'a> TokenizerContext<'a> {
-    fn new(input: &'a str) -> TokenizerContext {
-        TokenizerContext { input, index: 0 }
+    fn new(file_id: FileId, input: &'a str) -> TokenizerContext<'a> {
+        TokenizerContext {
+            input,
+            file_id,
+            index: 0,
+            line: 1,
+            col: 1,
+        }
     }
 
     fn rest_input(&self) -> &'a str {
@@ -31,9 +161,34 @@ impl<'a> TokenizerContext<'a> {
     }
 
     fn seek(&mut self, steps: usize) {
+        for c in self.input[self.index..self.index + steps].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
         self.index += steps;
     }
 
+    fn span(&self, len: usize) -> Span {
+        return Span {
+            file_id: self.file_id,
+            line: self.line,
+            col: self.col,
+            index: self.index,
+            len,
+        };
+    }
+
+    fn new_token(&self, kind: TokenKind<'a>, len: usize) -> Token<'a> {
+        return Token {
+            kind,
+            span: self.span(len),
+        };
+    }
+
     pub fn remains(&self) -> bool {
         return self.input.len() > self.index;
     }
@@ -41,44 +196,56 @@ impl<'a> TokenizerContext<'a> {
     /// returns true if any whitespace skipped.
     pub fn skip_whitespace(&mut self) -> bool {
         let rest_input = self.rest_input();
-
-        let mut i: usize = 0;
-        while let Some(_) = rest_input.chars().nth(i).filter(|c| c.is_whitespace()) {
-            i += 1;
-        }
+        let i = rest_input.len() - rest_input.trim_start().len();
         self.seek(i);
         return i > 0;
     }
 
-    pub fn consume_sign(&mut self) -> Option<Token<'a>> {
+    /// returns true if any comment skipped.
+    pub fn skip_comment(&mut self) -> Result<bool, Diagnostic> {
         let rest_input = self.rest_input();
 
-        for sign in SIGNES {
-            if rest_input.starts_with(sign) {
-                let token = Token {
-                    kind: TokenKind::Sign(sign),
-                    line_of_code: self.input,
-                    index: self.index,
-                };
-                self.seek(sign.len());
-                return Some(token);
-            }
+        if rest_input.starts_with("//") {
+            let len = rest_input.find('\n').unwrap_or(rest_input.len());
+            self.seek(len);
+            return Ok(true);
         }
-        return None;
+        if let Some(body) = rest_input.strip_prefix("/*") {
+            let len = match body.find("*/") {
+                Some(i) => i + 4,
+                None => return Err(self.error("コメントが閉じられていません。", 2)),
+            };
+            self.seek(len);
+            return Ok(true);
+        }
+        return Ok(false);
+    }
+
+    /// 最長一致で区切り子を読む
+    pub fn consume_sign(&mut self) -> Option<Token<'a>> {
+        let rest_input = self.rest_input();
+
+        let signs = SIGNES.iter().map(|sign| (*sign, *sign));
+        let (spelling, sign) = signs
+            .chain(DIGRAPHS.iter().cloned())
+            .filter(|(spelling, _)| rest_input.starts_with(spelling))
+            .max_by_key(|(spelling, _)| spelling.len())?;
+        let token = self.new_token(TokenKind::Sign(sign), spelling.len());
+        self.seek(spelling.len());
+        return Some(token);
     }
 
     pub fn consume_keyword(&mut self) -> Option<Token<'a>> {
         let rest_input = self.rest_input();
 
         for (keyword, value) in Keyword::PAIRS {
-            let trailing = rest_input.chars().nth(keyword.len());
-
-            if rest_input.starts_with(keyword) && trailing.filter(is_alnum).is_none() {
-                let token = Token {
-                    kind: TokenKind::Keyword(*value),
-                    line_of_code: self.input,
-                    index: self.index,
-                };
+            if !rest_input.starts_with(keyword) {
+                continue;
+            }
+            let trailing = rest_input[keyword.len()..].chars().next();
+
+            if trailing.filter(is_alnum).is_none() {
+                let token = self.new_token(TokenKind::Keyword(*value), keyword.len());
                 self.seek(keyword.len());
                 return Some(token);
             }
@@ -86,27 +253,21 @@ impl<'a> TokenizerContext<'a> {
         return None;
     }
 
-    pub fn consume_number(&mut self) -> Option<Token<'a>> {
+    pub fn consume_number(&mut self) -> Result<Option<Token<'a>>, Diagnostic> {
         let rest_input = self.rest_input();
-        let mut num: Option<u32> = None;
-        let mut i: usize = 0;
-        while let Some(n) = rest_input.chars().nth(i).and_then(|c| c.to_digit(10)) {
-            num = num.map(|num| num * 10 + n).or_else(|| Some(n));
-            i += 1;
-        }
-
-        match num {
-            Some(n) => {
-                let token = Token {
-                    kind: TokenKind::Number(n),
-                    line_of_code: &self.input,
-                    index: self.index,
-                };
-                self.seek(i);
-                return Some(token);
-            }
-            None => return None,
+        if !rest_input.starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(None);
         }
+        let len = rest_input
+            .find(|c: char| !is_alnum(&c))
+            .unwrap_or(rest_input.len());
+
+        let result = parse_int_literal(&rest_input[..len]);
+        let token = result.map(|literal| self.new_token(TokenKind::Number(literal), len));
+        let token = token.map_err(|msg| self.error(msg, len));
+        // NOTE: エラーの場合も続きを読めるようにリテラル全体を読み飛ばす
+        self.seek(len);
+        return token.map(Some);
     }
 
     pub fn consume_ident(&mut self) -> Option<Token<'a>> {
@@ -122,11 +283,7 @@ impl<'a> TokenizerContext<'a> {
         }
 
         if i > 0 {
-            let token = Token {
-                kind: TokenKind::Ident(&rest_input[0..i]),
-                line_of_code: &self.input,
-                index: self.index,
-            };
+            let token = self.new_token(TokenKind::Ident(&rest_input[0..i]), i);
             self.seek(i);
             return Some(token);
         }
@@ -134,50 +291,99 @@ impl<'a> TokenizerContext<'a> {
         return None;
     }
 
-    pub fn consume_string_literal(&mut self) -> Option<Token<'a>> {
-        let rest_input = self.rest_input();
-        let mut iter = rest_input.chars();
-        if iter.next().filter(|&c| c == '"').is_none() {
-            return None;
-        }
+    /// quote で囲まれたリテラルを読み、エスケープを解いた中身と読んだバイト数を返す
+    fn read_quoted(&self, quote: u8) -> Result<(Vec<u8>, usize), Diagnostic> {
+        let rest_input = self.rest_input().as_bytes();
+        let mut bytes = Vec::new();
         let mut i: usize = 1;
         loop {
-            if let Some(c) = iter.next() {
-                if c == '"' {
-                    break;
+            match rest_input.get(i) {
+                None | Some(b'\n') => {
+                    return Err(self.error("リテラルが閉じられていません。", 1));
+                }
+                Some(c) if *c == quote => return Ok((bytes, i + 1)),
+                Some(b'\\') => {
+                    let (c, len) = decode_escape(&rest_input[i + 1..])
+                        .map_err(|msg| self.error_at(i, msg, 1))?;
+                    bytes.push(c);
+                    i += 1 + len;
+                }
+                Some(c) => {
+                    bytes.push(*c);
+                    i += 1;
                 }
-                i += 1;
-            } else {
-                self.report_error("文字列が閉じられていません。");
             }
         }
-        let token = Token {
-            kind: TokenKind::Str(&rest_input[1..i]),
-            line_of_code: &self.input,
-            index: self.index,
+    }
+
+    pub fn consume_string_literal(&mut self) -> Result<Option<Token<'a>>, Diagnostic> {
+        if !self.rest_input().starts_with('"') {
+            return Ok(None);
+        }
+        let (mut bytes, len) = self.read_quoted(b'"')?;
+        // NOTE: For string termination: '\0'
+        bytes.push(0);
+        let token = self.new_token(TokenKind::Str(bytes), len);
+        self.seek(len);
+        return Ok(Some(token));
+    }
+
+    pub fn consume_char_literal(&mut self) -> Result<Option<Token<'a>>, Diagnostic> {
+        if !self.rest_input().starts_with('\'') {
+            return Ok(None);
+        }
+        let (bytes, len) = self.read_quoted(b'\'')?;
+        let c = match bytes.as_slice() {
+            [c] => *c,
+            [] => return Err(self.error("文字定数が空です。", len)),
+            _ => return Err(self.error("文字定数には一文字だけ書けます。", len)),
         };
-        self.seek(i + 1);
-        return Some(token);
+        let token = self.new_token(TokenKind::Char(c), len);
+        self.seek(len);
+        return Ok(Some(token));
     }
 
-    pub fn report_error(&self, msg: &str) -> ! {
-        let loc = self.input;
-        let i = self.index + 1;
-        panic!("\n{0}\n{1:>2$} {3}\n", loc, '^', i, msg);
+    /// 現在位置から offset バイト先を指すエラー (offset までに改行がないこと)
+    fn error_at(&self, offset: usize, msg: &str, len: usize) -> Diagnostic {
+        let mut span = self.span(len);
+        span.index += offset;
+        span.col += self.rest_input()[..offset].chars().count();
+        return Diagnostic::error(msg).with_span(span);
+    }
+
+    pub fn error(&self, msg: &str, len: usize) -> Diagnostic {
+        return Diagnostic::error(msg).with_span(self.span(len));
     }
 }
 
-pub fn tokenize<'a>(input: &'a str) -> Vec<Token<'a>> {
+pub fn tokenize(file_id: FileId, input: &str) -> Result<Vec<Token<'_>>, Vec<Diagnostic>> {
     let mut tokens = Vec::new();
-    let mut ctx = TokenizerContext::new(&input);
+    let mut diagnostics = Vec::new();
+    let mut ctx = TokenizerContext::new(file_id, input);
 
     while ctx.remains() {
         if ctx.skip_whitespace() {
             continue;
         }
-        if let Some(token) = ctx.consume_number() {
-            tokens.push(token);
-            continue;
+        match ctx.skip_comment() {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(diagnostic) => {
+                // NOTE: 閉じられていないコメントは入力の最後まで続くので打ち切る
+                diagnostics.push(diagnostic);
+                break;
+            }
+        }
+        match ctx.consume_number() {
+            Ok(Some(token)) => {
+                tokens.push(token);
+                continue;
+            }
+            Ok(None) => {}
+            Err(diagnostic) => {
+                diagnostics.push(diagnostic);
+                continue;
+            }
         }
         if let Some(token) = ctx.consume_sign() {
             tokens.push(token);
@@ -191,19 +397,34 @@ pub fn tokenize<'a>(input: &'a str) -> Vec<Token<'a>> {
             tokens.push(token);
             continue;
         }
-        if let Some(token) = ctx.consume_string_literal() {
-            tokens.push(token);
-            continue;
+        match ctx
+            .consume_string_literal()
+            .transpose()
+            .or_else(|| ctx.consume_char_literal().transpose())
+        {
+            Some(Ok(token)) => {
+                tokens.push(token);
+                continue;
+            }
+            None => {}
+            Some(Err(diagnostic)) => {
+                // NOTE: 壊れたリテラルの後ろは正しく区切れないので打ち切る
+                diagnostics.push(diagnostic);
+                break;
+            }
         }
-        ctx.report_error("トークナイズ出来ません。");
+        // NOTE: 読めない文字は報告して読み飛ばし、続きのエラーもまとめて報告する
+        let len = ctx.rest_input().chars().next().map_or(1, |c| c.len_utf8());
+        diagnostics.push(ctx.error("トークナイズ出来ません。", len));
+        ctx.seek(len);
     }
 
-    tokens.push(Token {
-        kind: TokenKind::Eof,
-        line_of_code: input,
-        index: input.len(),
-    });
-    return tokens;
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    tokens.push(ctx.new_token(TokenKind::Eof, 0));
+    return Ok(tokens);
 }
 
 #[cfg(test)]
@@ -212,97 +433,252 @@ mod tests {
 
     #[test]
     fn test_remains() {
-        assert_eq!(TokenizerContext::new("").remains(), false);
-        assert_eq!(TokenizerContext::new("123").remains(), true);
-        assert_eq!(TokenizerContext::new(" ").remains(), true);
+        assert!(!TokenizerContext::new(0, "").remains());
+        assert!(TokenizerContext::new(0, "123").remains());
+        assert!(TokenizerContext::new(0, " ").remains());
     }
 
     #[test]
     fn test_skip_whitespace() {
-        let mut ctx = TokenizerContext::new("   123");
-        assert_eq!(ctx.skip_whitespace(), true);
+        let mut ctx = TokenizerContext::new(0, "   123");
+        assert!(ctx.skip_whitespace());
         assert_eq!(ctx.rest_input(), "123");
 
-        let mut ctx = TokenizerContext::new("   ");
-        assert_eq!(ctx.skip_whitespace(), true);
+        let mut ctx = TokenizerContext::new(0, "   ");
+        assert!(ctx.skip_whitespace());
         assert_eq!(ctx.rest_input(), "");
 
-        let mut ctx = TokenizerContext::new("123   ");
-        assert_eq!(ctx.skip_whitespace(), false);
+        let mut ctx = TokenizerContext::new(0, "123   ");
+        assert!(!ctx.skip_whitespace());
         assert_eq!(ctx.rest_input(), "123   ");
     }
 
+    #[test]
+    fn test_skip_comment() {
+        let mut ctx = TokenizerContext::new(0, "// abc\n123");
+        assert!(ctx.skip_comment().unwrap());
+        assert_eq!(ctx.rest_input(), "\n123");
+
+        let mut ctx = TokenizerContext::new(0, "/* a\n * b */123");
+        assert!(ctx.skip_comment().unwrap());
+        assert_eq!(ctx.rest_input(), "123");
+        assert_eq!((ctx.line, ctx.col), (2, 8));
+
+        let mut ctx = TokenizerContext::new(0, "/ 2");
+        assert!(!ctx.skip_comment().unwrap());
+        assert_eq!(ctx.rest_input(), "/ 2");
+
+        let errors = tokenize(0, "1;\n  /* abc\n").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let span = errors[0].span.unwrap();
+        assert_eq!((span.line, span.col, span.len), (2, 3, 2));
+    }
+
     #[test]
     fn consume_sign() {
-        let mut ctx = TokenizerContext::new("123");
+        let mut ctx = TokenizerContext::new(0, "123");
         assert!(ctx.consume_sign().is_none());
         assert_eq!(ctx.rest_input(), "123");
 
-        let mut ctx = TokenizerContext::new("+-+123");
+        let mut ctx = TokenizerContext::new(0, "+-+123");
         assert!(ctx.consume_sign().is_some());
         assert_eq!(ctx.rest_input(), "-+123");
+
+        let cases = [
+            ("<<=1", "<<=", "1"),
+            ("<<1", "<<", "1"),
+            ("->x", "->", "x"),
+            ("...)", "...", ")"),
+            ("..", ".", "."),
+            ("+++", "++", "+"),
+            ("&&=", "&&", "="),
+            ("%=2", "%=", "2"),
+            ("<:0:>", "[", "0:>"),
+            ("%:%:x", "##", "x"),
+            ("%:x", "#", "x"),
+            ("<%", "{", ""),
+        ];
+        for (input, sign, rest) in &cases {
+            let mut ctx = TokenizerContext::new(0, input);
+            assert_eq!(ctx.consume_sign().unwrap().kind, TokenKind::Sign(sign));
+            assert_eq!(ctx.rest_input(), *rest);
+        }
     }
 
     #[test]
     fn test_consume_keyword() {
-        let mut ctx = TokenizerContext::new("returna");
+        let mut ctx = TokenizerContext::new(0, "returna");
         assert!(ctx.consume_keyword().is_none());
         assert_eq!(ctx.rest_input(), "returna");
 
-        let mut ctx = TokenizerContext::new("return");
+        let mut ctx = TokenizerContext::new(0, "return");
         assert!(ctx.consume_keyword().is_some());
         assert_eq!(ctx.rest_input(), "");
 
-        let mut ctx = TokenizerContext::new("return;");
+        let mut ctx = TokenizerContext::new(0, "return;");
         assert!(ctx.consume_keyword().is_some());
         assert_eq!(ctx.rest_input(), ";");
     }
 
+    fn number(value: u64, unsigned: bool, long: bool, decimal: bool) -> TokenKind<'static> {
+        return TokenKind::Number(IntLiteral {
+            value,
+            unsigned,
+            long,
+            decimal,
+        });
+    }
+
     #[test]
     fn test_consume_number() {
-        let mut ctx = TokenizerContext::new("");
-        assert!(ctx.consume_number().is_none());
+        let mut ctx = TokenizerContext::new(0, "");
+        assert!(ctx.consume_number().unwrap().is_none());
         assert_eq!(ctx.rest_input(), "");
 
-        let mut ctx = TokenizerContext::new("123");
-        assert_eq!(ctx.consume_number().unwrap().kind, TokenKind::Number(123));
+        let mut ctx = TokenizerContext::new(0, "123");
+        let token = ctx.consume_number().unwrap().unwrap();
+        assert_eq!(token.kind, number(123, false, false, true));
         assert_eq!(ctx.rest_input(), "");
 
-        let mut ctx = TokenizerContext::new("12+3");
-        assert_eq!(ctx.consume_number().unwrap().kind, TokenKind::Number(12));
+        let mut ctx = TokenizerContext::new(0, "12+3");
+        let token = ctx.consume_number().unwrap().unwrap();
+        assert_eq!(token.kind, number(12, false, false, true));
         assert_eq!(ctx.rest_input(), "+3");
 
-        let mut ctx = TokenizerContext::new("nan");
-        assert!(ctx.consume_number().is_none());
+        let mut ctx = TokenizerContext::new(0, "nan");
+        assert!(ctx.consume_number().unwrap().is_none());
         assert_eq!(ctx.rest_input(), "nan");
+
+        let cases = [
+            ("0", number(0, false, false, false)),
+            ("0x2aUL", number(42, true, true, false)),
+            ("0XfF", number(255, false, false, false)),
+            ("052", number(42, false, false, false)),
+            ("0b101010", number(42, false, false, false)),
+            ("42u", number(42, true, false, true)),
+            ("42llu", number(42, true, true, true)),
+            ("42LL", number(42, false, true, true)),
+            ("18446744073709551615", number(u64::MAX, false, false, true)),
+        ];
+        for (input, kind) in &cases {
+            let mut ctx = TokenizerContext::new(0, input);
+            assert_eq!(ctx.consume_number().unwrap().unwrap().kind, *kind);
+            assert_eq!(ctx.rest_input(), "");
+        }
+
+        for input in &[
+            "18446744073709551616",
+            "0x",
+            "08",
+            "0b102",
+            "12abc",
+            "12lL",
+            "12uu",
+        ] {
+            let mut ctx = TokenizerContext::new(0, input);
+            assert!(ctx.consume_number().is_err(), "{}", input);
+            assert_eq!(ctx.rest_input(), "");
+        }
     }
 
     #[test]
     fn test_consume_ident() {
-        let mut ctx = TokenizerContext::new("1abc");
+        let mut ctx = TokenizerContext::new(0, "1abc");
         assert!(ctx.consume_ident().is_none());
         assert_eq!(ctx.rest_input(), "1abc");
 
-        let mut ctx = TokenizerContext::new("a1bc");
+        let mut ctx = TokenizerContext::new(0, "a1bc");
         assert_eq!(ctx.consume_ident().unwrap().kind, TokenKind::Ident("a1bc"));
         assert_eq!(ctx.rest_input(), "");
 
-        let mut ctx = TokenizerContext::new("ab1c+2");
+        let mut ctx = TokenizerContext::new(0, "ab1c+2");
         assert_eq!(ctx.consume_ident().unwrap().kind, TokenKind::Ident("ab1c"));
         assert_eq!(ctx.rest_input(), "+2");
     }
 
     #[test]
     fn test_consume_string_literal() {
-        let mut ctx = TokenizerContext::new("abc");
-        assert!(ctx.consume_string_literal().is_none());
+        let mut ctx = TokenizerContext::new(0, "abc");
+        assert!(ctx.consume_string_literal().unwrap().is_none());
         assert_eq!(ctx.rest_input(), "abc");
 
-        let mut ctx = TokenizerContext::new("\"abc\"");
+        let mut ctx = TokenizerContext::new(0, "\"abc\"");
         assert_eq!(
-            ctx.consume_string_literal().unwrap().kind,
-            TokenKind::Str("abc")
+            ctx.consume_string_literal().unwrap().unwrap().kind,
+            TokenKind::Str(b"abc\0".to_vec())
         );
         assert_eq!(ctx.rest_input(), "");
+
+        let mut ctx = TokenizerContext::new(0, r#""a\"b\n\t\\\0\101\x41\e" 1"#);
+        assert_eq!(
+            ctx.consume_string_literal().unwrap().unwrap().kind,
+            TokenKind::Str(b"a\"b\n\t\\\0AA\x1b\0".to_vec())
+        );
+        assert_eq!(ctx.rest_input(), " 1");
+
+        let mut ctx = TokenizerContext::new(0, "\"abc");
+        assert!(ctx.consume_string_literal().is_err());
+
+        let mut ctx = TokenizerContext::new(0, "\"abc\nd\"");
+        assert!(ctx.consume_string_literal().is_err());
+
+        let mut ctx = TokenizerContext::new(0, r#""ab\x100""#);
+        let span = ctx.consume_string_literal().unwrap_err().span.unwrap();
+        assert_eq!((span.index, span.col), (3, 4));
+    }
+
+    #[test]
+    fn test_consume_char_literal() {
+        let mut ctx = TokenizerContext::new(0, "abc");
+        assert!(ctx.consume_char_literal().unwrap().is_none());
+
+        let mut ctx = TokenizerContext::new(0, "'a'+1");
+        assert_eq!(
+            ctx.consume_char_literal().unwrap().unwrap().kind,
+            TokenKind::Char(b'a')
+        );
+        assert_eq!(ctx.rest_input(), "+1");
+
+        let mut ctx = TokenizerContext::new(0, r"'\''");
+        assert_eq!(
+            ctx.consume_char_literal().unwrap().unwrap().kind,
+            TokenKind::Char(b'\'')
+        );
+
+        let mut ctx = TokenizerContext::new(0, r"'\377'");
+        assert_eq!(
+            ctx.consume_char_literal().unwrap().unwrap().kind,
+            TokenKind::Char(0xff)
+        );
+
+        assert!(TokenizerContext::new(0, "''")
+            .consume_char_literal()
+            .is_err());
+        assert!(TokenizerContext::new(0, "'ab'")
+            .consume_char_literal()
+            .is_err());
+        assert!(TokenizerContext::new(0, "'a")
+            .consume_char_literal()
+            .is_err());
+    }
+
+    #[test]
+    fn test_tokenize_error() {
+        let errors = tokenize(0, "1 $ 2\n @").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        let span = errors[0].span.unwrap();
+        assert_eq!((span.index, span.line, span.col), (2, 1, 3));
+        let span = errors[1].span.unwrap();
+        assert_eq!((span.index, span.line, span.col), (7, 2, 2));
+    }
+
+    #[test]
+    fn test_line_and_column() {
+        let tokens = tokenize(3, "int\n  x;\n").unwrap();
+        let positions: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.span.file_id, t.span.line, t.span.col))
+            .collect();
+        assert_eq!(positions, vec![(3, 1, 1), (3, 2, 3), (3, 2, 4), (3, 3, 1)]);
     }
 }
@@ -1,3 +1,5 @@
+use crate::diagnostic::{Diagnostic, Span};
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Keyword {
     Return,
@@ -5,9 +7,27 @@ pub enum Keyword {
     Else,
     While,
     For,
+    Do,
+    Break,
+    Continue,
+    Goto,
+    Switch,
+    Case,
+    Default,
     SizeOf,
+    AlignOf,
+    Void,
+    Bool,
     Char,
+    Short,
     Int,
+    Long,
+    Signed,
+    Unsigned,
+    Struct,
+    Union,
+    Enum,
+    Typedef,
 }
 
 impl Keyword {
@@ -17,9 +37,27 @@ impl Keyword {
         ("else", Keyword::Else),
         ("while", Keyword::While),
         ("for", Keyword::For),
+        ("do", Keyword::Do),
+        ("break", Keyword::Break),
+        ("continue", Keyword::Continue),
+        ("goto", Keyword::Goto),
+        ("switch", Keyword::Switch),
+        ("case", Keyword::Case),
+        ("default", Keyword::Default),
         ("sizeof", Keyword::SizeOf),
+        ("_Alignof", Keyword::AlignOf),
+        ("void", Keyword::Void),
+        ("_Bool", Keyword::Bool),
         ("char", Keyword::Char),
+        ("short", Keyword::Short),
         ("int", Keyword::Int),
+        ("long", Keyword::Long),
+        ("signed", Keyword::Signed),
+        ("unsigned", Keyword::Unsigned),
+        ("struct", Keyword::Struct),
+        ("union", Keyword::Union),
+        ("enum", Keyword::Enum),
+        ("typedef", Keyword::Typedef),
     ];
 }
 
@@ -30,31 +68,43 @@ impl std::fmt::Display for Keyword {
                 return write!(f, "{}", s);
             }
         }
-        panic!("Unexpected keyword token: \"{:?}\"", self);
+        return write!(f, "{:?}", self);
     }
 }
 
+/// 整数リテラル
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct IntLiteral {
+    pub value: u64,
+    // "u" / "U"
+    pub unsigned: bool,
+    // "l" / "L" / "ll" / "LL"
+    pub long: bool,
+    // NOTE: 10進数かそれ以外かで型の決め方が異なる
+    pub decimal: bool,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum TokenKind<'a> {
-    Number(u32),
+    Number(IntLiteral),
     Sign(&'a str),
     Ident(&'a str),
     Keyword(Keyword),
-    Str(&'a str),
+    // 文字列リテラル (エスケープ済み、終端の '\0' を含む)
+    Str(Vec<u8>),
+    // 文字定数
+    Char(u8),
     Eof,
 }
 
 #[derive(Debug)]
 pub struct Token<'a> {
     pub kind: TokenKind<'a>,
-    pub line_of_code: &'a str,
-    pub index: usize,
+    pub span: Span,
 }
 
 impl<'a> Token<'a> {
-    pub fn report_error(&self, msg: &str) -> ! {
-        let loc = self.line_of_code;
-        let i = self.index + 1;
-        panic!("\n{0}\n{1:>2$} {3}\n", loc, '^', i, msg);
+    pub fn error(&self, msg: &str) -> Diagnostic {
+        return Diagnostic::error(msg).with_span(self.span);
     }
 }
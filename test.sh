@@ -23,8 +23,7 @@ assert() {
   expected="$1"
   input="$2"
 
-  ./target/debug/pcc "$input" > tmp.s
-  cc -no-pie -o tmp tmp.s tmp2.o
+  ./target/debug/pcc -o tmp -e "$input" tmp2.o
   set +e
   ./tmp
   actual="$?"
@@ -38,6 +37,24 @@ assert() {
   fi
 }
 
+# コンパイルエラーになり、診断メッセージに expected が含まれることを確かめる
+assert_error() {
+  expected="$1"
+  input="$2"
+
+  set +e
+  actual=$(./target/debug/pcc -S -o /dev/null -e "$input" 2>&1)
+  status="$?"
+  set -e
+
+  if [ "$status" != 0 ] && [[ "$actual" == *"$expected"* ]]; then
+    echo "$input => error"
+  else
+    echo "$input => \"$expected\" expected, but got \"$actual\""
+    exit 1
+  fi
+}
+
 echo "===== E2E testing ====="
 
 assert 0 "int main() { return 0; }"
@@ -109,8 +126,8 @@ assert 5 "int main() { int x=3; int *y=&x; *y=5; return x; }"
 assert 1 "int main() { int a; a=1; int *p; p=&a; return *p; }"
 assert 8 'int main() { int x=3; int y=5; return foo(&x,y); } int foo(int *x, int y) { return *x+y; }'
 
-assert 8 'int main() { int x; return sizeof(x); }'
-assert 8 'int main() { int x; return sizeof x; }'
+assert 4 'int main() { int x; return sizeof(x); }'
+assert 4 'int main() { int x; return sizeof x; }'
 assert 8 'int main() { int* x; return sizeof x; }'
 assert 8 'int main() { int** x; return sizeof x; }'
 
@@ -118,25 +135,25 @@ assert 3 'int main() { int x[2]; int *y=&x; *y=3; return *x; }'
 assert 3 'int main() { int x[3]; *x=3; *(x+1)=4; *(x+2)=5; return *x; }'
 assert 4 'int main() { int x[3]; *x=3; *(x+1)=4; *(x+2)=5; return *(x+1); }'
 assert 5 'int main() { int x[3]; *x=3; *(x+1)=4; *(x+2)=5; return *(x+2); }'
-assert 0 'int main() { int x[2][3]; int *y=x; *y=0; return **x; }'
-assert 1 'int main() { int x[2][3]; int *y=x; *(y+1)=1; return *(*x+1); }'
-assert 2 'int main() { int x[2][3]; int *y=x; *(y+2)=2; return *(*x+2); }'
-assert 3 'int main() { int x[2][3]; int *y=x; *(y+3)=3; return **(x+1); }'
-assert 4 'int main() { int x[2][3]; int *y=x; *(y+4)=4; return *(*(x+1)+1); }'
-assert 5 'int main() { int x[2][3]; int *y=x; *(y+5)=5; return *(*(x+1)+2); }'
-assert 6 'int main() { int x[2][3]; int *y=x; *(y+6)=6; return **(x+2); }'
+assert 0 'int main() { int x[2][3]; int *y=(int *)x; *y=0; return **x; }'
+assert 1 'int main() { int x[2][3]; int *y=(int *)x; *(y+1)=1; return *(*x+1); }'
+assert 2 'int main() { int x[2][3]; int *y=(int *)x; *(y+2)=2; return *(*x+2); }'
+assert 3 'int main() { int x[2][3]; int *y=(int *)x; *(y+3)=3; return **(x+1); }'
+assert 4 'int main() { int x[2][3]; int *y=(int *)x; *(y+4)=4; return *(*(x+1)+1); }'
+assert 5 'int main() { int x[2][3]; int *y=(int *)x; *(y+5)=5; return *(*(x+1)+2); }'
+assert 6 'int main() { int x[2][3]; int *y=(int *)x; *(y+6)=6; return **(x+2); }'
 assert 3 'int main() { int x[3]; *x=3; x[1]=4; x[2]=5; return *x; }'
 assert 4 'int main() { int x[3]; *x=3; x[1]=4; x[2]=5; return *(x+1); }'
 assert 5 'int main() { int x[3]; *x=3; x[1]=4; x[2]=5; return *(x+2); }'
 assert 5 'int main() { int x[3]; *x=3; x[1]=4; x[2]=5; return *(x+2); }'
 assert 5 'int main() { int x[3]; *x=3; x[1]=4; 2[x]=5; return *(x+2); }'
-assert 0 'int main() { int x[2][3]; int *y=x; *y=0; return **x; }'
-assert 1 'int main() { int x[2][3]; int *y=x; *(y+1)=1; return *(*x+1); }'
-assert 2 'int main() { int x[2][3]; int *y=x; *(y+2)=2; return *(*x+2); }'
-assert 3 'int main() { int x[2][3]; int *y=x; *(y+3)=3; return **(x+1); }'
-assert 4 'int main() { int x[2][3]; int *y=x; *(y+4)=4; return *(*(x+1)+1); }'
-assert 5 'int main() { int x[2][3]; int *y=x; *(y+5)=5; return *(*(x+1)+2); }'
-assert 6 'int main() { int x[2][3]; int *y=x; *(y+6)=6; return **(x+2); }'
+assert 0 'int main() { int x[2][3]; int *y=(int *)x; *y=0; return **x; }'
+assert 1 'int main() { int x[2][3]; int *y=(int *)x; *(y+1)=1; return *(*x+1); }'
+assert 2 'int main() { int x[2][3]; int *y=(int *)x; *(y+2)=2; return *(*x+2); }'
+assert 3 'int main() { int x[2][3]; int *y=(int *)x; *(y+3)=3; return **(x+1); }'
+assert 4 'int main() { int x[2][3]; int *y=(int *)x; *(y+4)=4; return *(*(x+1)+1); }'
+assert 5 'int main() { int x[2][3]; int *y=(int *)x; *(y+5)=5; return *(*(x+1)+2); }'
+assert 6 'int main() { int x[2][3]; int *y=(int *)x; *(y+6)=6; return **(x+2); }'
 
 assert 0 'int x; int main() { return x; }'
 assert 3 'int x; int main() { x=3; return x; }'
@@ -144,8 +161,8 @@ assert 0 'int x[4]; int main() { x[0]=0; x[1]=1; x[2]=2; x[3]=3; return x[0]; }'
 assert 1 'int x[4]; int main() { x[0]=0; x[1]=1; x[2]=2; x[3]=3; return x[1]; }'
 assert 2 'int x[4]; int main() { x[0]=0; x[1]=1; x[2]=2; x[3]=3; return x[2]; }'
 assert 3 'int x[4]; int main() { x[0]=0; x[1]=1; x[2]=2; x[3]=3; return x[3]; }'
-assert 8 'int x; int main() { return sizeof(x); }'
-assert 32 'int x[4]; int main() { return sizeof(x); }'
+assert 4 'int x; int main() { return sizeof(x); }'
+assert 16 'int x[4]; int main() { return sizeof(x); }'
 
 assert 1 'int main() { char x=1; return x; }'
 assert 1 'int main() { char x=1; char y=2; return x; }'
@@ -159,4 +176,445 @@ assert 99 'int main() { return "abc"[2]; }'
 assert 0 'int main() { return "abc"[3]; }'
 assert 4 'int main() { return sizeof("abc"); }'
 
+assert 2 'int main() { /* return 1; */ return 2; }'
+assert 2 'int main() { // return 1;
+return 2; }'
+assert 3 'int main() { int x=3; /* multi
+line
+comment */ return x; }'
+
+assert 7 'int main() { return "\a"[0]; }'
+assert 8 'int main() { return "\b"[0]; }'
+assert 9 'int main() { return "\t"[0]; }'
+assert 10 'int main() { return "\n"[0]; }'
+assert 11 'int main() { return "\v"[0]; }'
+assert 12 'int main() { return "\f"[0]; }'
+assert 13 'int main() { return "\r"[0]; }'
+assert 27 'int main() { return "\e"[0]; }'
+assert 0 'int main() { return "\0"[0]; }'
+assert 34 'int main() { return "a\"b"[1]; }'
+assert 4 'int main() { return sizeof("a\"b"); }'
+assert 92 'int main() { return "\\"[0]; }'
+assert 65 'int main() { return "\101"[0]; }'
+assert 104 'int main() { return "\1500"[0]; }'
+assert 48 'int main() { return "\1500"[1]; }'
+assert 65 'int main() { return "\x41"[0]; }'
+assert 1 'int main() { return "\xff"[0] == 0-1; }'
+
+assert 97 "int main() { return 'a'; }"
+assert 10 "int main() { return '\\n'; }"
+assert 39 "int main() { return '\\''; }"
+assert 34 "int main() { return '\"'; }"
+assert 255 "int main() { return '\\377'; }"
+assert 1 "int main() { char x='\\xff'; return x == 0-1; }"
+
+assert 42 'int main() { return 0x2a; }'
+assert 42 'int main() { return 0X2A; }'
+assert 42 'int main() { return 052; }'
+assert 42 'int main() { return 0b101010; }'
+assert 29 'int main() { return 0b11 + 011 + 0x11; }'
+assert 42 'int main() { return 42u + 0l + 0LL + 0ull; }'
+assert 2 'int main() { return 4294967296 / 2147483648; }'
+assert 1 'int main() { return 0xffffffffffffffff == 0-1; }'
+assert 8 'int main() { return sizeof(0x7fffffffffffffffL); }'
+
+assert 3 'int main() <% int x<:2:>; x<:1:>=3; return x<:1:>; %>'
+
+assert 0 'int main() { return !1; }'
+assert 1 'int main() { return !0; }'
+assert 0 'int main() { return !!0; }'
+assert 1 'int main() { return !!42; }'
+assert 1 'int main() { return 1 && 2; }'
+assert 0 'int main() { return 1 && 0; }'
+assert 0 'int main() { return 0 && 1; }'
+assert 1 'int main() { return 0 || 2; }'
+assert 0 'int main() { return 0 || 0; }'
+assert 1 'int main() { return 1 || 0 && 0; }'
+assert 1 'int main() { return 1 == 1 && 2 < 3; }'
+assert 3 'int main() { int x=3; 0 && (x=5); return x; }'
+assert 3 'int main() { int x=3; 1 || (x=5); return x; }'
+assert 5 'int main() { int x=3; 1 && (x=5); return x; }'
+assert 1 'int main() { int *p=0; return !p || *p; }'
+
+assert 5 'int main() { return 17 % 6; }'
+assert 1 'int main() { return 1 + 6 % 3 * 2 - 0 / 5 + 0; }'
+assert 1 'int main() { return 3 & 1; }'
+assert 10 'int main() { return 10 & 14; }'
+assert 3 'int main() { return 1 | 2; }'
+assert 19 'int main() { return 0b10000 | 0b00011; }'
+assert 52 'int main() { return 0b111000 ^ 0b001100; }'
+assert 255 'int main() { return ~0; }'
+assert 0 'int main() { return ~(0-1); }'
+assert 8 'int main() { return 1 << 3; }'
+assert 2 'int main() { return 5 >> 1; }'
+assert 255 'int main() { return (0-1) >> 1; }'
+assert 15 'int main() { return 18446744073709551615u >> 60; }'
+assert 5 'int main() { return 18446744073709551615u % 10; }'
+assert 1 'int main() { return 18446744073709551615u / 2 > 0; }'
+assert 254 'int main() { return (0-4) / 2; }'
+assert 255 'int main() { return (0-7) % 2; }'
+assert 3 'int main() { return 1 | 2 ^ 3 & 1; }'
+assert 6 'int main() { return 1 + 2 << 1; }'
+assert 1 'int main() { return 4 < 1 << 3; }'
+assert 1 'int main() { return 3 & 3 == 3; }'
+
+assert 7 'int main() { int i=2; i+=5; return i; }'
+assert 7 'int main() { int i=2; return i+=5; }'
+assert 3 'int main() { int i=5; i-=2; return i; }'
+assert 6 'int main() { int i=3; i*=2; return i; }'
+assert 3 'int main() { int i=6; i/=2; return i; }'
+assert 2 'int main() { int i=17; i%=5; return i; }'
+assert 40 'int main() { int i=5; i<<=3; return i; }'
+assert 5 'int main() { int i=40; i>>=3; return i; }'
+assert 2 'int main() { int i=6; i&=3; return i; }'
+assert 7 'int main() { int i=6; i|=3; return i; }'
+assert 5 'int main() { int i=6; i^=3; return i; }'
+assert 3 'int main() { int i=2; return ++i; }'
+assert 1 'int main() { int i=2; return --i; }'
+assert 2 'int main() { int i=2; return i++; }'
+assert 2 'int main() { int i=2; return i--; }'
+assert 3 'int main() { int i=2; i++; return i; }'
+assert 1 'int main() { int i=2; i--; return i; }'
+assert 45 'int main() { int i; int j=0; for (i=0; i<10; i++) j+=i; return j; }'
+assert 5 'int main() { int a[3]; a[0]=3; a[1]=5; a[2]=7; int *p=a; p++; return *p; }'
+assert 7 'int main() { int a[3]; a[0]=3; a[1]=5; a[2]=7; int *p=a; p+=2; return *p; }'
+assert 3 'int main() { int a[3]; a[0]=3; a[1]=5; a[2]=7; int *p=a+2; p-=2; return *p; }'
+assert 5 'int main() { int a[3]; a[0]=3; a[1]=5; a[2]=7; int *p=a; return *++p; }'
+assert 3 'int main() { int a[3]; a[0]=3; a[1]=5; a[2]=7; int *p=a; return *p++; }'
+assert 6 'int main() { int a[3]; a[0]=3; a[1]=5; a[2]=7; int i=0; a[i++]+=3; return a[0]+i*0; }'
+assert 1 'int main() { int a[3]; a[0]=3; a[1]=5; a[2]=7; int i=0; a[i++]+=3; return i; }'
+assert 128 'int main() { char c=127; c++; return c; }'
+assert 0 'int main() { char c=255; ++c; return c; }'
+
+assert 2 'int main() { return 1 ? 2 : 3; }'
+assert 3 'int main() { return 0 ? 2 : 3; }'
+assert 4 'int main() { return 0 ? 2 : 0 ? 3 : 4; }'
+assert 5 'int main() { int x=0; 1 ? (x=5) : (x=7); return x; }'
+assert 7 'int main() { int x=0; 0 ? (x=5) : (x=7); return x; }'
+assert 8 'int main() { int x=3; return (x > 2 ? 8 : 9); }'
+assert 5 'int main() { int a[2]; a[0]=3; a[1]=5; int *p = 1 ? a+1 : 0; return *p; }'
+assert 1 'int main() { int *p = 0 ? 0 : 0; return !p; }'
+assert 8 'int main() { return sizeof(1 ? 18446744073709551615u : 1); }'
+assert 1 'int main() { return (1 ? 0-1 : 18446744073709551615u) >> 63; }'
+assert 3 'int main() { return (1, 2, 3); }'
+assert 7 'int main() { int i; int j; i=(j=3, j+4); return i; }'
+assert 10 'int main() { int i; int j; for (i=0, j=0; i<5; i++, j+=2) 0; return j; }'
+assert 3 'int main() { int x=1; (x++, x++); return x; }'
+assert 8 'int main() { char a[8]; return sizeof((0, a)); }'
+assert 3 'int main() { return add(1, (0, 2)); }'
+
+assert 3 'int main() { int i=0; for(;i<10;i++) { if (i == 3) break; } return i; }'
+assert 4 'int main() { int i=0; while (1) { if (i++ == 3) break; } return i; }'
+assert 3 'int main() { int i=0; for(;i<10;i++) { for (;;) break; if (i == 3) break; } return i; }'
+assert 4 'int main() { int i=0; while (1) { while(1) break; if (i++ == 3) break; } return i; }'
+assert 10 'int main() { int i=0; int j=0; for (;i<10;i++) { if (i>5) continue; j++; } return i; }'
+assert 6 'int main() { int i=0; int j=0; for (;i<10;i++) { if (i>5) continue; j++; } return j; }'
+assert 10 'int main() { int i=0; int j=0; for(;!i;) { for (;j!=10;j++) continue; break; } return j; }'
+assert 11 'int main() { int i=0; int j=0; while (i++<10) { if (i>5) continue; j++; } return i; }'
+assert 5 'int main() { int i=0; int j=0; while (i++<10) { if (i>5) continue; j++; } return j; }'
+assert 7 'int main() { int i=0; int j=0; do { j++; } while (i++ < 6); return j; }'
+assert 1 'int main() { int i=0; do { i++; } while (0); return i; }'
+assert 4 'int main() { int i=0; do { if (++i == 4) break; } while (1); return i; }'
+assert 5 'int main() { int i=0; int j=0; do { if (i++ % 2) continue; j++; } while (i < 10); return j; }'
+assert 3 'int main() { int i=0; goto a; a: i++; b: i++; c: i++; return i; }'
+assert 2 'int main() { int i=0; goto e; d: i++; e: i++; f: i++; return i; }'
+assert 1 'int main() { int i=0; goto i; g: i++; h: i++; i: i++; return i; }'
+assert 10 'int main() { int i=0; loop: i++; if (i < 10) goto loop; return i; }'
+
+assert 5 'int main() { int i=0; switch(0) { case 0:i=5;break; case 1:i=6;break; case 2:i=7;break; } return i; }'
+assert 6 'int main() { int i=0; switch(1) { case 0:i=5;break; case 1:i=6;break; case 2:i=7;break; } return i; }'
+assert 7 'int main() { int i=0; switch(2) { case 0:i=5;break; case 1:i=6;break; case 2:i=7;break; } return i; }'
+assert 0 'int main() { int i=0; switch(3) { case 0:i=5;break; case 1:i=6;break; case 2:i=7;break; } return i; }'
+assert 5 'int main() { int i=0; switch(0) { case 0:i=5;break; default:i=7; } return i; }'
+assert 7 'int main() { int i=0; switch(1) { case 0:i=5;break; default:i=7; } return i; }'
+assert 2 'int main() { int i=0; switch(1) { case 0: 0; case 1: 0; case 2: 0; i=2; } return i; }'
+assert 0 'int main() { int i=0; switch(3) { case 0: 0; case 1: 0; case 2: 0; i=2; } return i; }'
+assert 5 'int main() { int i=0; switch(1) { case 0: i++; case 1: i++; case 2: i+=2; case 3: i+=2; } return i; }'
+assert 3 'int main() { int i=0; switch(-1) { case 1: i=1; break; case -1: i=3; break; } return i; }'
+assert 10 'int main() { int i=0; int j=0; for (;i<10;i++) { switch (i) { case 3: continue; } j++; } return i; }'
+assert 9 'int main() { int i=0; int j=0; for (;i<10;i++) { switch (i) { case 3: continue; } j++; } return j; }'
+assert 13 'int main() { int i=0; switch (2 + 1) { case 1 + 2: i=13; break; case 1 << 1: i=2; } return i; }'
+assert 40 'int main() { int i=4; int j=0; switch (i) { case 0: j=0; break; case 1: j=10; break; case 2: j=20; break; case 4: j=40; break; case 5: j=50; break; default: j=1; } return j; }'
+assert 1 'int main() { int i=3; int j=0; switch (i) { case 0: j=0; break; case 1: j=10; break; case 2: j=20; break; case 4: j=40; break; case 5: j=50; break; default: j=1; } return j; }'
+assert 1 'int main() { int i=9; int j=0; switch (i) { case 0: j=0; break; case 1: j=10; break; case 2: j=20; break; case 4: j=40; break; case 5: j=50; break; default: j=1; } return j; }'
+assert 1 'int main() { int i=0-1; int j=0; switch (i) { case 0: j=0; break; case 1: j=10; break; case 2: j=20; break; case 4: j=40; break; case 5: j=50; break; default: j=1; } return j; }'
+assert 55 'int main() { int i=0; int j=0; for (; i<=10; i++) switch (i) { case 1: case 2: case 3: case 4: case 5: case 6: case 7: case 8: case 9: case 10: j+=i; } return j; }'
+
+assert 2 'int main() { int x=2; { int x=3; } return x; }'
+assert 2 'int main() { int x=2; { int x=3; } { int y=4; return x; } }'
+assert 3 'int main() { int x=2; { x=3; } return x; }'
+assert 5 'int main() { int x=2; { int x=3; { int x=5; return x; } } }'
+assert 4 'int main() { int x=2; { int x=3; x=4; { int y=x; return y; } } }'
+assert 45 'int main() { int j=0; for (int i=0; i<10; i++) j+=i; return j; }'
+assert 7 'int main() { int i=7; for (int i=0; i<10; i++) 0; return i; }'
+assert 3 'int main() { int i=3; for (int i=0; i<10; i++) { int i=5; } return i; }'
+assert 10 'int x; int main() { x=10; { int x=3; } return x; }'
+assert 3 'int x; int main() { x=10; int x=3; return x; }'
+assert 2 'int f(int x) { { int x=1; } return x; } int main() { return f(2); }'
+
+assert 1 'int main() { struct {int a; int b;} x; x.a=1; x.b=2; return x.a; }'
+assert 2 'int main() { struct {int a; int b;} x; x.a=1; x.b=2; return x.b; }'
+assert 1 'int main() { struct {char a; int b; char c;} x; x.a=1; x.b=2; x.c=3; return x.a; }'
+assert 3 'int main() { struct {char a; int b; char c;} x; x.b=1; x.b=2; x.c=3; return x.c; }'
+assert 0 'int main() { struct {char a; char b;} x[3]; char *p=(char *)x; p[0]=0; return x[0].a; }'
+assert 1 'int main() { struct {char a; char b;} x[3]; char *p=(char *)x; p[1]=1; return x[0].b; }'
+assert 2 'int main() { struct {char a; char b;} x[3]; char *p=(char *)x; p[2]=2; return x[1].a; }'
+assert 3 'int main() { struct {char a; char b;} x[3]; char *p=(char *)x; p[3]=3; return x[1].b; }'
+assert 6 'int main() { struct {char a[3]; char b[5];} x; char *p=(char *)&x; x.a[0]=6; return p[0]; }'
+assert 7 'int main() { struct {char a[3]; char b[5];} x; char *p=(char *)&x; x.b[0]=7; return p[3]; }'
+assert 6 'int main() { struct { struct { char b; } a; } x; x.a.b=6; return x.a.b; }'
+assert 4 'int main() { struct {int a;} x; return sizeof(x); }'
+assert 8 'int main() { struct {int a; int b;} x; return sizeof(x); }'
+assert 8 'int main() { struct {char a; int b;} x; return sizeof(x); }'
+assert 3 'int main() { struct {char a; char b; char c;} x; return sizeof(x); }'
+assert 12 'int main() { struct {int a[3];} x; return sizeof(x); }'
+assert 16 'int main() { struct {int a;} x[4]; return sizeof(x); }'
+assert 24 'int main() { struct {int a[3];} x[2]; return sizeof(x); }'
+assert 2 'int main() { struct {char a; char b;} x; return sizeof(x); }'
+assert 1 'int main() { struct {char a; int b;} x; char y; return sizeof(x) - sizeof(y) * 7; }'
+assert 8 'int main() { struct t {int a; int b;} x; struct t y; return sizeof(y); }'
+assert 8 'int main() { struct t {int a; int b;}; struct t y; return sizeof(y); }'
+assert 2 'int main() { struct t {char a[2];}; { struct t {char a[4];}; } struct t y; return sizeof(y); }'
+assert 3 'int main() { struct t {int x;}; int t=1; struct t y; y.x=2; return t+y.x; }'
+assert 3 'int main() { struct t {char a;} x; struct t *y = &x; x.a=3; return y->a; }'
+assert 3 'int main() { struct t {char a;} x; struct t *y = &x; y->a=3; return x.a; }'
+assert 8 'int main() { union { int a; char b[6]; } x; return sizeof(x); }'
+assert 3 'int main() { union { int a; char b[4]; } x; x.a = 515; return x.b[0]; }'
+assert 2 'int main() { union { int a; char b[4]; } x; x.a = 515; return x.b[1]; }'
+assert 0 'int main() { union { int a; char b[4]; } x; x.a = 515; return x.b[2]; }'
+assert 4 'int main() { union { struct { char a; char b; }; int c; } x; x.c = 0; x.b = 4; return x.b; }'
+assert 1 'int main() { struct { char a; struct { char b; int c; }; } x; x.c = 1; return x.c; }'
+assert 7 'int main() { struct t {int a; int b;} x; struct t y; x.a=7; y=x; return y.a; }'
+assert 5 'int main() { struct t {int a; char b;} x; struct t y; x.b=5; y=x; return y.b; }'
+assert 3 'int main() { struct t {int a; int b;} x; struct t *p=&x; struct t y; y.a=3; *p=y; return x.a; }'
+assert 6 'struct node { int val; struct node *next; }; int main() { struct node a; struct node b; a.val=1; b.val=5; a.next=&b; b.next=0; return a.val + a.next->val; }'
+assert 10 'struct p { int x; int y; } g; int main() { g.x=3; g.y=7; return g.x + g.y; }'
+assert 3 'struct s; struct s *p; struct s { int x; }; int main() { struct s v; p=&v; p->x=3; return v.x; }'
+
+assert 0 'int main() { enum { zero, one, two }; return zero; }'
+assert 1 'int main() { enum { zero, one, two }; return one; }'
+assert 2 'int main() { enum { zero, one, two }; return two; }'
+assert 5 'int main() { enum { five=5, six, seven }; return five; }'
+assert 6 'int main() { enum { five=5, six, seven }; return six; }'
+assert 0 'int main() { enum { zero, five=5, three=3, four }; return zero; }'
+assert 5 'int main() { enum { zero, five=5, three=3, four }; return five; }'
+assert 3 'int main() { enum { zero, five=5, three=3, four }; return three; }'
+assert 4 'int main() { enum { zero, five=5, three=3, four }; return four; }'
+assert 4 'int main() { enum { zero, one, two } x; return sizeof(x); }'
+assert 4 'int main() { enum t { zero, one, two }; enum t y; return sizeof(y); }'
+assert 3 'int main() { enum { a=1, b=a+1, c=a|b, }; return c; }'
+assert 2 'int main() { enum color { red, green, blue } c = blue; return c; }'
+assert 12 'int main() { enum { N=3 }; int a[N]; return sizeof(a); }'
+assert 16 'enum { N=2 }; int g[N*2]; int main() { return sizeof(g); }'
+assert 20 'int main() { enum { A=10, B=20 }; int x=B; switch (x) { case A: return 10; case B: return 20; } return 0; }'
+assert 1 'int main() { int x=1; { enum { x=5 }; } return x; }'
+assert 5 'int main() { int x=1; { enum { x=5 }; return x; } }'
+assert 2 'enum e { A, B, C }; int main() { enum e v = C; return v; }'
+
+assert 1 'int main() { typedef int t; t x=1; return x; }'
+assert 1 'int main() { typedef struct {int a;} t; t x; x.a=1; return x.a; }'
+assert 1 'int main() { typedef int t; { t t=1; return t; } }'
+assert 2 'int main() { typedef struct {int a;} t; { typedef int t; } t x; x.a=2; return x.a; }'
+assert 4 'typedef int t; t g; int main() { g=4; return g; }'
+assert 8 'typedef int t; int main() { t *p; return sizeof(p); }'
+assert 12 'typedef int a3[3]; int main() { a3 x; return sizeof(x); }'
+assert 3 'typedef struct node node; struct node { int val; node *next; }; int main() { node a; node b; a.val=1; b.val=2; a.next=&b; return a.val + a.next->val; }'
+assert 3 'typedef int t; int main() { int x=3; { int t=x; return t; } }'
+assert 6 'typedef int t; int main() { t x=2; { t y=3; x=x*y; } return x; }'
+assert 5 'typedef int t; int f(t a) { return a; } int main() { return f(5); }'
+assert 3 'int main() { typedef enum { A, B, C, D } letter; letter l = D; return l; }'
+assert 7 'typedef int t; int main() { int i=0; for (t j=0; j<7; j++) i++; return i; }'
+
+assert 2 'int main() { short x; return sizeof(x); }'
+assert 4 'int main() { int x; return sizeof(x); }'
+assert 8 'int main() { long x; return sizeof(x); }'
+assert 8 'int main() { long long x; return sizeof(x); }'
+assert 1 'int main() { _Bool x; return sizeof(x); }'
+assert 4 'int main() { unsigned x; return sizeof(x); }'
+assert 2 'int main() { unsigned short int x; return sizeof(x); }'
+assert 8 'int main() { long unsigned int x; return sizeof(x); }'
+assert 1 'int main() { signed char x; return sizeof(x); }'
+assert 16 'int main() { struct {char a; long b;} x; return sizeof(x); }'
+assert 4 'int main() { struct {char a; short b;} x; return sizeof(x); }'
+assert 1 'int main() { int x=-1; long y=x; return y == -1; }'
+assert 255 'int main() { int x=-1; unsigned char y=x; return y; }'
+assert 1 'int main() { short x=-1; return x == -1; }'
+assert 255 'int main() { unsigned short x=65535; return x & 255; }'
+assert 1 'int main() { unsigned short x=65535; return x == 65535; }'
+assert 1 'int main() { unsigned x=4294967295; long y=x; return y == 4294967295; }'
+assert 1 'int main() { long x=4294967296; int y=x; return y == 0; }'
+assert 1 'int main() { _Bool x=42; return x; }'
+assert 0 'int main() { _Bool x=0; return x; }'
+assert 1 'int main() { _Bool x=256; return x; }'
+assert 3 'int main() { long long x=3; return x; }'
+assert 7 'short f(short a, short b) { return a+b; } int main() { return f(3, 4); }'
+assert 7 'long f(long a, char b, short c, int d) { return a+b+c+d; } int main() { return f(1, 1, 2, 3); }'
+assert 1 'int main() { int x[2]; x[0]=-1; x[1]=2; return x[0] == -1 && x[1] == 2; }'
+assert 1 'int main() { return sub(3, 5) == -2; }'
+
+assert 1 'int main() { return 2147483647 + 1 < 0; }'
+assert 0 'int main() { int x=2147483647; x=x+1; return x > 0; }'
+assert 1 'int main() { char x=127; return x+1 == 128; }'
+assert 4 'int main() { char x=1; return sizeof(x+x); }'
+assert 4 'int main() { char x=1; return sizeof(+x); }'
+assert 4 'int main() { short x=1; return sizeof(~x); }'
+assert 8 'int main() { int x=1; long y=1; return sizeof(x+y); }'
+assert 8 'int main() { char x=1; return sizeof(x+1L); }'
+assert 0 'int main() { unsigned x=1; return -1 < x; }'
+assert 1 'int main() { int x=-1; return x < 1; }'
+assert 1 'int main() { unsigned x=0; x=x-1; return x == 4294967295; }'
+assert 1 'int main() { unsigned x=0; return x-1 > 0; }'
+assert 0 'int main() { long x=-1; unsigned y=1; return x > y; }'
+assert 1 'int main() { unsigned long x=-1; return x > 0; }'
+assert 127 'int main() { unsigned x=-1; return x >> 25; }'
+assert 255 'int main() { int x=-1; return (x >> 24) & 255; }'
+assert 1 'int main() { unsigned char x=255; return x+1 == 256; }'
+assert 44 'int main() { char x; return x=300; }'
+assert 1 'int main() { _Bool x; return (x=2) == 1; }'
+assert 1 'int main() { char x=100; x+=100; return x == -56; }'
+assert 1 'int main() { unsigned char x=200; x+=100; return x == 44; }'
+assert 1 'int main() { int x=-8; unsigned y=2; return x/y == 2147483644; }'
+assert 1 'int main() { unsigned short x=65535; int y=x; return y == 65535; }'
+assert 1 'int main() { int x=1; return (x ? -1 : 2u) == 4294967295; }'
+assert 1 'int main() { enum { N = 2147483647 + 1 }; return N < 0; }'
+
+assert 1 'int main() { return sizeof(char); }'
+assert 2 'int main() { return sizeof(short); }'
+assert 4 'int main() { return sizeof(int); }'
+assert 8 'int main() { return sizeof(long); }'
+assert 8 'int main() { return sizeof(char *); }'
+assert 12 'int main() { return sizeof(int[3]); }'
+assert 24 'int main() { return sizeof(int[2][3]); }'
+assert 8 'int main() { return sizeof(int (*)[3]); }'
+assert 24 'int main() { return sizeof(int *[3]); }'
+assert 8 'int main() { return sizeof(struct {int a; int b;}); }'
+assert 4 'typedef int t; int main() { return sizeof(t); }'
+assert 4 'int main() { return _Alignof(int); }'
+assert 1 'int main() { return _Alignof(char[3]); }'
+assert 8 'int main() { return _Alignof(struct {char a; long b;}); }'
+assert 4 'int main() { int x; return sizeof (x); }'
+assert 1 'int main() { return (char)257; }'
+assert 255 'int main() { return (unsigned char)-1; }'
+assert 1 'int main() { return (char)255 == -1; }'
+assert 1 'int main() { return (short)65535 == -1; }'
+assert 1 'int main() { return (unsigned short)-1 == 65535; }'
+assert 1 'int main() { return (long)-1 == -1; }'
+assert 1 'int main() { return (unsigned)-1 == 4294967295; }'
+assert 1 'int main() { return (int)4294967297 == 1; }'
+assert 1 'int main() { return (_Bool)256; }'
+assert 0 'int main() { return (_Bool)0; }'
+assert 8 'int main() { return sizeof((long)1); }'
+assert 1 'int main() { return -(char)255; }'
+assert 3 'int main() { int x[3]; x[2]=3; long p=(long)x; return *(int *)(p+8); }'
+assert 2 'int main() { int x[2][3]; long p=(long)x; ((int (*)[3])p)[1][0]=2; return x[1][0]; }'
+assert 1 'int main() { return (char)1 * (char)1; }'
+assert 8 'int main() { char a[(char)255 == -1 ? 8 : 2]; return sizeof(a); }'
+assert 8 'int main() { char a[(unsigned char)-1 == 255 ? 8 : 2]; return sizeof(a); }'
+
+assert 3 'void f(int *p) { *p=3; } int main() { int x; f(&x); return x; }'
+assert 3 'void f(int *p) { *p=3; return; *p=5; } int main() { int x; f(&x); return x; }'
+assert 0 'void f() {} int main() { f(); return 0; }'
+assert 5 'int f(void) { return 5; } int main() { return f(); }'
+assert 8 'int main() { void *p; return sizeof(p); }'
+assert 3 'int main() { int x=3; void *p=&x; int *q=p; return *q; }'
+assert 3 'int main() { int x=3; int *p=&x; void *q=p; return *(int *)q; }'
+assert 1 'int main() { char a[2]; void *p=a; return (char *)(p+1) == a+1; }'
+assert 2 'int main() { int x=2; int *p=&x; void *q=0; return *(int *)(1 ? p : q); }'
+assert 0 'int main() { int x=1; (void)x; return 0; }'
+assert 3 'void f(int *p) { *p=3; } int main() { int x=0; 1 ? f(&x) : f(&x); return x; }'
+
+assert 3 'int f(int); int main() { return f(3); } int f(int x) { return x; }'
+assert 7 'int add(int, int); int main() { return add(3, 4); }'
+assert 1 'char *f(char *p); int main() { char s[3]; s[2]=1; return *(f(s)+2); } char *f(char *p) { return p; }'
+assert 8 'long f(); int main() { return sizeof(f()); } long f() { return 8; }'
+assert 1 'char *f(); int main() { return sizeof(f()) == 8; }'
+assert 1 'char f() { return 257; } int main() { return f(); }'
+assert 1 'int main() { long x=ret_long(); return x == -1; } long ret_long() { return -1; }'
+assert 255 'unsigned char f() { return -1; } int main() { return f(); }'
+assert 1 'short f(); int main() { return f() == -1; } short f() { return 65535; }'
+assert 1 '_Bool f(int x) { return x; } int main() { return f(256); }'
+assert 1 'long f(long x) { return x; } int main() { return f(1L << 40) == 1L << 40; }'
+assert 1 'int f(char x) { return x; } int main() { return f(257); }'
+assert 2 'int f(int *p) { return *p; } int main() { int x=2; void *p=&x; return f(p); }'
+assert 0 'int f(int *p) { return p == 0; } int main() { return f(0) - 1; }'
+assert 8 'int f(int a[3]) { return sizeof(a); } int main() { int x[3]; return f(x); }'
+assert 55 'int fib(int n); int main() { return fib(10); } int fib(int n) { if (n <= 1) return n; return fib(n-1) + fib(n-2); }'
+assert 3 'void f(void); int g; int main() { f(); return g; } void f(void) { g=3; }'
+
+assert 21 'int f(int a,int b,int c,int d,int e,int g) { return a+b+c+d+e+g; } int main() { return f(1,2,3,4,5,6); }'
+assert_error '7 個以上の引数には対応していません' 'int f(int a,int b,int c,int d,int e,int g,int h) { return 0; }'
+assert_error '7 個以上の引数には対応していません' 'int f(int,int,int,int,int,int,int);'
+assert_error '7 個以上の引数には対応していません' 'int main() { return add(1,2,3,4,5,6,7); }'
+
+assert_error '配列が大きすぎます' 'int main(){ int a[0x7fffffff]; return 0; }'
+assert_error '配列が大きすぎます' 'char x[1073741824][4]; int main(){ return 0; }'
+assert_error '配列が大きすぎます' 'int main(){ return sizeof(int[0x7fffffff]); }'
+assert_error '配列が大きすぎます' 'struct { int a[1<<30]; int b[1<<30]; } s; int main(){ return 0; }'
+assert_error '構造体が大きすぎます' 'struct { int a[1<<29]; int b[1<<29]; } s; int main(){ return 0; }'
+assert 0 'int main(){ union { int a[1<<29]; int b[1<<29]; } *p; return 0; }'
+assert_error 'ローカル変数の領域が大きすぎます' 'int main(){ char a[0x7fffffff]; char b[0x7fffffff]; char c[2]; return 0; }'
+assert 12 'int main(){ char x[3][4]; return sizeof(x); }'
+assert 1 'int main(){ unsigned x=4294967295; switch(x){case -1: return 1; default: return 2;} }'
+assert 1 'int main(){ char c=-1; switch(c){case 255: return 2; case -1: return 1;} return 3; }'
+assert 3 'int main(){ unsigned char c=255; switch(c){case -1: return 2;} return 3; }'
+assert_error 'case の値 4294967295 が重複しています' 'int main(){ unsigned x=1; switch(x){case -1: case 4294967295: return 1;} return 0; }'
+assert_error 'switch 文の条件式は整数でなければなりません' 'int main(){ int *p=0; switch(p){case 0: return 1;} return 0; }'
+assert_error 'switch 文の条件式は整数でなければなりません' 'int main(){ struct {int a;} s; switch(s){case 0: return 1;} return 0; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; return s + 1; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; s += 1; return 0; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; s++; return 0; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; return -s; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; return !s; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; return s == s; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; return s && 1; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; return s ? 1 : 2; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; if (s) return 1; return 0; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; while (s) return 1; return 0; }'
+assert_error '構造体の値はこの演算には使用できません' 'int main(){ struct {int a;} s; for (;s;) return 1; return 0; }'
+assert 3 'struct S {int a;}; int main(){ struct S s; struct S t; t.a=3; s = t; return s.a; }'
+assert 8 'int main(){ int a[3]; return sizeof(a+1); }'
+assert 8 'int main(){ int a[3]; return sizeof(a-1); }'
+assert 8 'int main(){ int a[3]; return sizeof(1+a); }'
+assert 8 'int main(){ char a[2][3]; return sizeof(a+1); }'
+assert 3 'int main(){ char a[2][3]; return sizeof(*(a+1)); }'
+assert 3 'int main(){ int x=3; void *v=&x; int *p=v; return *p; }'
+assert 3 'int main(){ int x=3; int *p; void *v; v=&x; p=v; return *p; }'
+assert 3 'void *f(int *p){ return p; } int main(){ int x=3; int *p=f(&x); return *p; }'
+assert 1 'int main(){ int *p=0; p=0 ? 0 : 0; return !p; }'
+assert 1 'int main(){ int x; _Bool b=&x; return b; }'
+assert_error '代入する値の型が異なります' 'int main(){ int *p; int x; p = x; return 0; }'
+assert_error '代入する値の型が異なります' 'int main(){ int *p; int x; x = p; return 0; }'
+assert_error '代入する値の型が異なります' 'int main(){ char *q; int *p = (char *)q; return 0; }'
+assert_error '代入する値の型が異なります' 'int main(){ int x; long *p = &x; return 0; }'
+assert_error '代入する値の型が異なります' 'int main(){ int x = 1; int *p = x; return 0; }'
+assert_error '戻り値の型が関数の型と異なります' 'int *f(char *p){ return p; } int main(){ return 0; }'
+assert_error '戻り値の型が関数の型と異なります' 'int f(int *p){ return p; } int main(){ return 0; }'
+assert_error '構造体の値渡しには対応していません' 'struct S {int a;}; struct S f(); int main(){ return 0; }'
+assert_error '構造体の値渡しには対応していません' 'struct S {int a;}; struct S f(){ struct S s; return s; } int main(){ return 0; }'
+assert 3 'struct S {int a;}; struct S *f(struct S *p){ return p; } int main(){ struct S s; s.a=3; return f(&s)->a; }'
+assert_error '整数型の値が必要です' 'int main(){ int x=3; int *p=&x; return *(p*1); }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; return p / 2; }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; return p % 2; }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; return 2 & p; }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; return p | 1; }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; return p ^ 1; }'
+assert_error '整数型の値が必要です' 'int main(){ int a[2]; return a << 1; }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; return 1 >> p; }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; return ~p; }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; p *= 2; return 0; }'
+assert_error '整数型の値が必要です' 'int main(){ int *p=0; p <<= 1; return 0; }'
+assert_error '整数型の値が必要です' 'int main(){ int x=1; int *p=0; x &= p; return 0; }'
+assert 8 'int main(){ int x=2; x <<= 2; return x; }'
+assert_error '構造体の値渡しには対応していません' 'struct S{int a;int b;}; int take(); int main(){ struct S s; s.a=3; s.b=4; return take(s); }'
+assert_error '構造体の値渡しには対応していません' 'struct S{int a;}; int main(){ struct S s; return take(1, s); }'
+assert_error '条件演算子の両辺の構造体の型が異なります' 'int main(){ struct A {int a;} a; struct B {int a;} b; a = 1 ? a : b; return 0; }'
+assert_error '条件演算子の両辺の構造体の型が異なります' 'int main(){ struct A {int a;} a; int x = 1 ? a : 0; return 0; }'
+assert 4 'int main(){ struct A {int a;} a; struct A b; struct A c; a.a=3; b.a=4; c = 0 ? a : b; return c.a; }'
+assert_error '列挙定数 "A" の値 4294967296 は int 型で表せません' 'enum { A = 4294967296 }; int main(){ return 0; }'
+assert_error '列挙定数 "B" の値 2147483648 は int 型で表せません' 'enum { A = 2147483647, B }; int main(){ return 0; }'
+assert_error '列挙定数 "A" の値 -2147483649 は int 型で表せません' 'enum { A = -2147483649 }; int main(){ return 0; }'
+assert 1 'enum { A = 2147483647, B = -2147483648 }; int main(){ return A > 0 && B < 0; }'
+assert_error '"f" が同じスコープで再定義されています' 'int f(); int f; int main(){ return 0; }'
+assert_error '"A" が同じスコープで再定義されています' 'enum { A }; int A; int main(){ return 0; }'
+assert_error '"t" が同じスコープで再定義されています' 'typedef int t; int t; int main(){ return 0; }'
 echo -e "\e[32mAll tests passed!\e[0m"